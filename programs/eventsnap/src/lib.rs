@@ -1,7 +1,94 @@
 use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_lang::solana_program::{
+    ed25519_program,
+    hash::hashv,
+    sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+};
+use anchor_spl::associated_token::{get_associated_token_address_with_program_id, AssociatedToken};
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_2022_extensions::spl_token_metadata_interface::state::Field;
+use anchor_spl::token_interface::{
+    mint_to, token_group_initialize, token_member_initialize, token_metadata_initialize,
+    token_metadata_update_field, transfer_checked, Mint, MintTo, TokenAccount, TokenGroupInitialize,
+    TokenInterface, TokenMemberInitialize, TokenMetadataInitialize, TokenMetadataUpdateField,
+    TransferChecked,
+};
 
 declare_id!("9B1F56Dx649qbEDRbQAXZtmPXTFrLaYjTXBuCeZWMJ1x");
 
+#[constant]
+pub const PROGRAM_DATA_SEED: &[u8] = b"program_data";
+#[constant]
+pub const EVENT_SEED: &[u8] = b"event";
+#[constant]
+pub const USER_SEED: &[u8] = b"user";
+#[constant]
+pub const ATTENDEES_SEED: &[u8] = b"attendees";
+#[constant]
+pub const HIGHLIGHTS_SEED: &[u8] = b"highlights";
+#[constant]
+pub const ROLE_SEED: &[u8] = b"role";
+#[constant]
+pub const BAN_SEED: &[u8] = b"ban";
+#[constant]
+pub const VAULT_SEED: &[u8] = b"vault";
+#[constant]
+pub const BADGE_SEED: &[u8] = b"badge";
+#[constant]
+pub const COLLECTION_SEED: &[u8] = b"collection";
+#[constant]
+pub const IMAGE_TREE_SEED: &[u8] = b"image_tree";
+#[constant]
+pub const MAX_IMAGE_TREE_DEPTH: u8 = 24;
+#[constant]
+pub const MAX_IMAGE_TREE_BUFFER_SIZE: u8 = 64;
+#[constant]
+pub const ATTENDEES_PER_PAGE: u32 = 128;
+#[constant]
+pub const HIGHLIGHTS_PER_PAGE: u32 = 32;
+
+// Bits of `ProgramData.paused`; deletions and withdrawals are never paused
+#[constant]
+pub const PAUSE_CREATE_EVENT: u8 = 1 << 0;
+#[constant]
+pub const PAUSE_JOIN_EVENT: u8 = 1 << 1;
+#[constant]
+pub const PAUSE_UPLOAD_IMAGE: u8 = 1 << 2;
+#[constant]
+pub const PAUSE_ALL: u8 = PAUSE_CREATE_EVENT | PAUSE_JOIN_EVENT | PAUSE_UPLOAD_IMAGE;
+
+#[constant]
+pub const BPS_DENOMINATOR: u16 = 10_000;
+
+pub const MAX_UID_LEN: usize = 32;
+pub const MAX_NAME_LEN: usize = 50;
+pub const MAX_BANNER_LEN: usize = 200;
+pub const MAX_URL_LEN: usize = 200;
+pub const MAX_TAG_LEN: usize = 50;
+pub const MAX_CID_LEN: usize = 100;
+/// Token-2022 TLV entries for the group extensions: a 4 byte header plus the
+/// `TokenGroup` (update authority, mint, size, max size) or
+/// `TokenGroupMember` (mint, group, member number) state.
+const TOKEN_GROUP_LEN: usize = 4 + 32 + 32 + 4 + 4;
+const TOKEN_GROUP_MEMBER_LEN: usize = 4 + 32 + 32 + 4;
+pub const ARWEAVE_TX_ID_LEN: usize = 43;
+pub const SHADOW_DRIVE_URL_PREFIX: &str = "https://shdw-drive.genesysgo.net/";
+/// Prefix of the message an owner signs to invite someone to an event.
+pub const INVITE_PREFIX: &[u8] = b"eventsnap:invite";
+pub const BADGE_SYMBOL: &str = "SNAP";
+/// Additional metadata key holding the event uid on badges and photos.
+pub const BADGE_UID_KEY: &str = "uid";
+pub const PHOTO_SYMBOL: &str = "SNAPPIC";
+/// Additional metadata keys on minted photos. Royalty recipients are listed
+/// as extra `<creator pubkey> = <percent share>` entries; Token-2022 does not
+/// enforce them, so royalties are only paid by marketplaces that honour them.
+pub const COLLECTION_KEY: &str = "collection";
+pub const ROYALTY_BPS_KEY: &str = "royalty_basis_points";
+/// Additional metadata key holding the hex sha256 of the minted photo.
+pub const CONTENT_HASH_KEY: &str = "sha256";
+
 #[program]
 pub mod eventsnap {
     use super::*;
@@ -12,17 +99,86 @@ pub mod eventsnap {
         program_data.owner = ctx.accounts.owner.key();
         program_data.oracle = oracle;
         program_data.event_count = 0;
+        program_data.active_event_count = 0;
+        program_data.pending_owner = None;
+        program_data.paused = 0;
+        program_data.protocol_fee_bps = 0;
+        program_data.bump = ctx.bumps.program_data;
+
+        emit!(ProgramDataUpdated::from(&**program_data));
+        Ok(())
+    }
+
+    pub fn set_oracle(ctx: Context<UpdateProgramData>, oracle: Pubkey) -> Result<()> {
+        let program_data = &mut ctx.accounts.program_data;
+        program_data.oracle = oracle;
+        
+        emit!(ProgramDataUpdated::from(&**program_data));
+        Ok(())
+    }
+
+    // First step of an ownership transfer; the new owner must accept it
+    pub fn propose_owner(ctx: Context<UpdateProgramData>, new_owner: Pubkey) -> Result<()> {
+        let program_data = &mut ctx.accounts.program_data;
+        program_data.pending_owner = Some(new_owner);
+        
+        emit!(ProgramDataUpdated::from(&**program_data));
+        Ok(())
+    }
+
+    // Emergency stop for the instruction classes in `paused` (see `PAUSE_*`)
+    pub fn set_paused(ctx: Context<UpdateProgramData>, paused: u8) -> Result<()> {
+        require!(paused & !PAUSE_ALL == 0, EventError::InvalidPauseFlags);
+        
+        let program_data = &mut ctx.accounts.program_data;
+        program_data.paused = paused;
+        
+        emit!(ProgramDataUpdated::from(&**program_data));
+        Ok(())
+    }
+
+    // Share of every entry fee sent to `ProgramData.owner`
+    pub fn set_protocol_fee(ctx: Context<UpdateProgramData>, fee_bps: u16) -> Result<()> {
+        require!(fee_bps <= BPS_DENOMINATOR, EventError::InvalidProtocolFee);
+        
+        let program_data = &mut ctx.accounts.program_data;
+        program_data.protocol_fee_bps = fee_bps;
+        
+        emit!(ProgramDataUpdated::from(&**program_data));
+        Ok(())
+    }
+
+    pub fn accept_owner(ctx: Context<AcceptOwner>) -> Result<()> {
+        let program_data = &mut ctx.accounts.program_data;
+        program_data.owner = ctx.accounts.new_owner.key();
+        program_data.pending_owner = None;
+        
+        emit!(ProgramDataUpdated::from(&**program_data));
         Ok(())
     }
 
+    /// Creates an event with its owner as the first attendee. A `draft`
+    /// event takes no joins or uploads until the owner sets it `Active`.
+    #[allow(clippy::too_many_arguments)]
     pub fn create_event(
         ctx: Context<CreateEvent>, 
         uid: String,
         name: String,
         banner: String,
+        max_attendees: u32,
+        max_images_per_user: u32,
+        starts_at: i64,
+        ends_at: i64,
+        uploads_close_at: i64,
+        draft: bool,
         ) -> Result<()> {
-            require!(uid.len() <= 32, EventError::UidTooLong);
-            require!(name.len() <= 50, EventError::NameTooLong);
+            require!(uid.len() <= MAX_UID_LEN, EventError::UidTooLong);
+            require!(name.len() <= MAX_NAME_LEN, EventError::NameTooLong);
+            require!(banner.len() <= MAX_BANNER_LEN, EventError::BannerTooLong);
+            require!(
+                max_attendees > 0 && max_images_per_user > 0,
+                EventError::InvalidEventLimit
+            );
             
             let event = &mut ctx.accounts.event;
             let program_data = &mut ctx.accounts.program_data;
@@ -31,267 +187,4055 @@ pub mod eventsnap {
             event.name = name;
             event.banner = banner;
             event.owner = ctx.accounts.authority.key();
-            event.attendees = vec![ctx.accounts.authority.key()];
-            event.highlight_images = vec![];
+            event.attendee_count = 1;
+            event.highlight_count = 0;
+            event.role_count = 0;
+            event.ban_count = 0;
+            event.max_attendees = max_attendees;
+            event.max_images_per_user = max_images_per_user;
+            event.set_schedule(starts_at, ends_at, uploads_close_at)?;
+            event.status = if draft { EventStatus::Draft } else { EventStatus::Active };
+            event.bump = ctx.bumps.event;
+            
+            let vault = &mut ctx.accounts.vault;
+            vault.event = event.key();
+            vault.collected = 0;
+            vault.withdrawn = 0;
+            vault.bump = ctx.bumps.vault;
+            
+            let attendee_page = &mut ctx.accounts.attendee_page;
+            attendee_page.event = event.key();
+            attendee_page.page_no = 0;
+            attendee_page.attendees = vec![ctx.accounts.authority.key()];
+            attendee_page.bump = ctx.bumps.attendee_page;
             
             program_data.event_count = program_data.event_count.checked_add(1)
                 .ok_or(EventError::EventCountOverflow)?;
+            if !draft {
+                program_data.active_event_count = program_data.active_event_count.checked_add(1)
+                    .ok_or(EventError::EventCountOverflow)?;
+            }
+            
+            emit!(EventCreated {
+                event: event.key(),
+                uid: event.uid.clone(),
+                name: event.name.clone(),
+                banner: event.banner.clone(),
+                owner: event.owner,
+                max_attendees,
+                max_images_per_user,
+                starts_at,
+                ends_at,
+                uploads_close_at,
+                status: event.status,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
             
             Ok(())
         }
 
-        pub fn join_event(ctx: Context<JoinEvent>) -> Result<()> {  
+        pub fn join_event(ctx: Context<JoinEvent>, proof: Vec<[u8; 32]>) -> Result<()> {  
         let event = &mut ctx.accounts.event;
         let user_data = &mut ctx.accounts.user_data;
+        let attendee_page = &mut ctx.accounts.attendee_page;
         
         require!(!user_data.is_joined, EventError::AlreadyJoined);
-        require!(event.attendees.len() < 10, EventError::MaxAttendeesReached);
+        require!(event.status == EventStatus::Active, EventError::EventNotActive);
+        
+        let now = Clock::get()?.unix_timestamp;
+        require!(now >= event.starts_at, EventError::EventNotStarted);
+        require!(now < event.ends_at, EventError::EventEnded);
+        
+        let role = event.role_of(&ctx.accounts.authority.key(), ctx.accounts.member_role.as_deref());
+        require!(
+            event.attendee_count < event.max_attendees || role.is_some_and(Role::bypasses_limits),
+            EventError::MaxAttendeesReached
+        );
+        
+        // Anyone holding a role was already let in by the owner
+        if role.is_none() {
+            match event.access_mode {
+                AccessMode::Public => {}
+                AccessMode::InviteOnly => {
+                    let instructions = ctx.accounts.instructions
+                        .as_ref()
+                        .ok_or(EventError::InviteRequired)?;
+                    verify_invite(
+                        &instructions.to_account_info(),
+                        &event.owner,
+                        &invite_message(&event.key(), &ctx.accounts.authority.key()),
+                    )?;
+                }
+                AccessMode::Allowlist => require!(
+                    verify_allowlist_proof(&event.allowlist_root, &ctx.accounts.authority.key(), &proof),
+                    EventError::NotOnAllowlist
+                ),
+            }
+        }
+        
+        // Staff holding a role get in for free
+        let entry_fee = if role.is_none() { event.entry_fee_lamports } else { 0 };
+        if entry_fee > 0 {
+            let protocol_fee = (entry_fee as u128 * ctx.accounts.program_data.protocol_fee_bps as u128
+                / BPS_DENOMINATOR as u128) as u64;
+            let proceeds = entry_fee - protocol_fee;
+            transfer_lamports(
+                &ctx.accounts.system_program,
+                &ctx.accounts.authority,
+                ctx.accounts.protocol_fee_recipient.to_account_info(),
+                protocol_fee,
+            )?;
+            transfer_lamports(
+                &ctx.accounts.system_program,
+                &ctx.accounts.authority,
+                ctx.accounts.vault.to_account_info(),
+                proceeds,
+            )?;
+            ctx.accounts.vault.collected += proceeds;
+            user_data.entry_fee_paid = proceeds;
+        }
+        
+        let token_fee = if role.is_none() { event.token_entry_fee } else { 0 };
+        if token_fee > 0 {
+            let (Some(fee_mint), Some(payer_token_account), Some(event_token_account), Some(token_program)) = (
+                ctx.accounts.fee_mint.as_ref(),
+                ctx.accounts.payer_token_account.as_ref(),
+                ctx.accounts.event_token_account.as_ref(),
+                ctx.accounts.token_program.as_ref(),
+            ) else {
+                return err!(EventError::TokenAccountsRequired);
+            };
+            require!(event.fee_mint == Some(fee_mint.key()), EventError::FeeMintMismatch);
+            require_keys_eq!(
+                event_token_account.key(),
+                get_associated_token_address_with_program_id(&event.key(), &fee_mint.key(), &token_program.key()),
+                EventError::FeeMintMismatch
+            );
+            transfer_checked(
+                CpiContext::new(
+                    token_program.to_account_info(),
+                    TransferChecked {
+                        from: payer_token_account.to_account_info(),
+                        mint: fee_mint.to_account_info(),
+                        to: event_token_account.to_account_info(),
+                        authority: ctx.accounts.authority.to_account_info(),
+                    },
+                ),
+                token_fee,
+                fee_mint.decimals,
+            )?;
+            ctx.accounts.vault.token_collected += token_fee;
+        }
+        
+        attendee_page.event = event.key();
+        attendee_page.page_no = event.next_attendee_page();
+        attendee_page.bump = ctx.bumps.attendee_page;
+        // The owner has been on page 0 since `create_event`; joining only
+        // gives them a `UserData` to upload with
+        if ctx.accounts.authority.key() != event.owner {
+            attendee_page.attendees.push(ctx.accounts.authority.key());
+            event.attendee_count += 1;
+        }
         
-        event.attendees.push(ctx.accounts.authority.key());
+        user_data.owner = ctx.accounts.authority.key();
+        user_data.event = event.key();
         user_data.is_joined = true;
         user_data.uploader_selfie = String::new();
         user_data.images = vec![];
+        user_data.appears_in = vec![];
+        user_data.token_fee_paid = token_fee;
+        user_data.bump = ctx.bumps.user_data;
+        
+        emit!(AttendeeJoined {
+            event: event.key(),
+            uid: event.uid.clone(),
+            attendee: user_data.owner,
+            attendee_count: event.attendee_count,
+            timestamp: now,
+        });
         
         Ok(())
     }
 
+    /// Records a pending image. `content_hash` is the sha256 of the file
+    /// behind `storage`, so downloads can be checked against it.
     pub fn upload_image_with_tag(
     ctx: Context<UploadImageWithTag>,
-    url: String,
+    storage: StorageRef,
+    content_hash: [u8; 32],
     tag: String
     ) -> Result<()> {
         let user_data = &mut ctx.accounts.user_data;
-        let event = &mut ctx.accounts.event;
+        let event = &ctx.accounts.event;
         
         require!(user_data.is_joined, EventError::NotJoined);
-        require!(url.len() <= 200, EventError::UrlTooLong);
-        require!(tag.len() <= 50, EventError::TagTooLong);
-        require!(user_data.images.len() < 20, EventError::MaxImagesReached);
+        require!(event.image_storage == ImageStorage::Accounts, EventError::ImagesStoredInTree);
+        storage.validate()?;
+        require!(content_hash != [0; 32], EventError::MissingContentHash);
+        require!(tag.len() <= MAX_TAG_LEN, EventError::TagTooLong);
+        let role = event.role_of(&ctx.accounts.authority.key(), ctx.accounts.member_role.as_deref());
+        require!(
+            user_data.images.len() < event.max_images_per_user as usize
+                || role.is_some_and(Role::bypasses_limits),
+            EventError::MaxImagesReached
+        );
+        
+        require!(event.status == EventStatus::Active, EventError::EventNotActive);
+        
+        // Photos may keep coming in after the event ends, until uploads close
+        let now = Clock::get()?.unix_timestamp;
+        require!(now >= event.starts_at, EventError::EventNotStarted);
+        require!(now < event.uploads_close_at, EventError::UploadWindowClosed);
         
         let image = UploadedImage {
-            url,
+            storage,
+            content_hash,
             tag,
             uploader: ctx.accounts.authority.key(),
+            status: ImageStatus::Pending,
+            minted: false,
         };
         
-        user_data.images.push(image.clone());
-        event.highlight_images.push(image.url);
+        emit!(ImageUploaded {
+            event: event.key(),
+            uid: event.uid.clone(),
+            uploader: image.uploader,
+            url: image.storage.uri(),
+            content_hash,
+            tag: image.tag.clone(),
+            timestamp: now,
+        });
+        
+        // Images only reach the event gallery once the oracle approves them
+        user_data.images.push(image);
+        
+        Ok(())
+    }
+
+    pub fn approve_image(ctx: Context<ApproveImage>, image_index: u32) -> Result<()> {
+        let user_data = &mut ctx.accounts.user_data;
+        let event = &mut ctx.accounts.event;
+        let highlight_page = &mut ctx.accounts.highlight_page;
+        
+        let image = user_data.images
+            .get_mut(image_index as usize)
+            .ok_or(EventError::InvalidImageIndex)?;
+        require!(image.status == ImageStatus::Pending, EventError::ImageNotPending);
+        image.status = ImageStatus::Approved;
         
+        highlight_page.event = event.key();
+        highlight_page.page_no = event.next_highlight_page();
+        highlight_page.highlights.push(Highlight {
+            url: image.storage.uri(),
+            uploader: image.uploader,
+            content_hash: image.content_hash,
+        });
+        highlight_page.bump = ctx.bumps.highlight_page;
+        event.highlight_count += 1;
+        
+        emit!(ImageApproved {
+            event: event.key(),
+            uid: event.uid.clone(),
+            uploader: image.uploader,
+            url: image.storage.uri(),
+            tag: image.tag.clone(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    pub fn reject_image(ctx: Context<RejectImage>, image_index: u32) -> Result<()> {
+        let user_data = &mut ctx.accounts.user_data;
+        
+        let image = user_data.images
+            .get_mut(image_index as usize)
+            .ok_or(EventError::InvalidImageIndex)?;
+        require!(image.status == ImageStatus::Pending, EventError::ImageNotPending);
+        image.status = ImageStatus::Rejected;
+        
+        emit!(ImageRejected {
+            event: ctx.accounts.event.key(),
+            uid: ctx.accounts.event.uid.clone(),
+            uploader: image.uploader,
+            url: image.storage.uri(),
+            tag: image.tag.clone(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    pub fn set_selfie(ctx: Context<SetSelfie>, selfie: String) -> Result<()> {
+        require!(selfie.len() <= MAX_URL_LEN, EventError::UrlTooLong);
+        
+        emit!(SelfieSet {
+            event: ctx.accounts.event.key(),
+            uid: ctx.accounts.event.uid.clone(),
+            user: ctx.accounts.user_data.owner,
+            selfie: selfie.clone(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        ctx.accounts.user_data.uploader_selfie = selfie;
+        Ok(())
+    }
+
+    pub fn attest_face_match(ctx: Context<AttestFaceMatch>, image_index: u32) -> Result<()> {
+        let image_user_data = &ctx.accounts.image_user_data;
+        let subject_user_data = &mut ctx.accounts.subject_user_data;
+        
+        let image = image_user_data.images
+            .get(image_index as usize)
+            .ok_or(EventError::InvalidImageIndex)?;
+        require!(image.status == ImageStatus::Approved, EventError::ImageNotApproved);
+        require!(!subject_user_data.uploader_selfie.is_empty(), EventError::SelfieNotSet);
+        require!(
+            !subject_user_data.appears_in
+                .iter()
+                .any(|photo| photo.content_hash == image.content_hash && photo.uploader == image.uploader),
+            EventError::FaceMatchAlreadyRecorded
+        );
+        
+        subject_user_data.appears_in.push(Highlight {
+            url: image.storage.uri(),
+            uploader: image.uploader,
+            content_hash: image.content_hash,
+        });
+        
+        emit!(FaceMatchAttested {
+            event: ctx.accounts.event.key(),
+            uid: ctx.accounts.event.uid.clone(),
+            uploader: image.uploader,
+            url: image.storage.uri(),
+            subject: subject_user_data.owner,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Drops a face match, e.g. once the matched photo has been deleted or
+    /// removed; callable by the oracle or an event moderator.
+    pub fn remove_face_match(ctx: Context<RemoveFaceMatch>, match_index: u32) -> Result<()> {
+        let subject_user_data = &mut ctx.accounts.subject_user_data;
+        require!(
+            (match_index as usize) < subject_user_data.appears_in.len(),
+            EventError::InvalidImageIndex
+        );
+        let photo = subject_user_data.appears_in.remove(match_index as usize);
+
+        emit!(FaceMatchRemoved {
+            event: ctx.accounts.event.key(),
+            uid: ctx.accounts.event.uid.clone(),
+            uploader: photo.uploader,
+            url: photo.url,
+            subject: subject_user_data.owner,
+            removed_by: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
         Ok(())
     }
 
     pub fn delete_image(ctx: Context<DeleteImage>, image_index: u32) -> Result<()> {
         let user_data = &mut ctx.accounts.user_data;
+        let event = &mut ctx.accounts.event;
         
         require!(
             (image_index as usize) < user_data.images.len(),
             EventError::InvalidImageIndex
         );
         
-        user_data.images.remove(image_index as usize);
+        let image = user_data.images.remove(image_index as usize);
+        
+        emit!(ImageDeleted {
+            event: event.key(),
+            uid: event.uid.clone(),
+            uploader: image.uploader,
+            url: image.storage.uri(),
+            tag: image.tag.clone(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        if image.status != ImageStatus::Approved {
+            return Ok(());
+        }
+        
+        // Cascade into the event gallery so deleted photos stop showing up
+        let highlight_page = ctx.accounts.highlight_page
+            .as_mut()
+            .ok_or(EventError::HighlightNotFound)?;
+        let position = highlight_page.position(&image.content_hash, &image.uploader)
+            .ok_or(EventError::HighlightNotFound)?;
+        check_tail_page(
+            highlight_page.page_no,
+            event.highlight_tail_page(),
+            ctx.accounts.tail_highlight_page.is_some(),
+        )?;
+        remove_paged(
+            &mut highlight_page.highlights,
+            position,
+            ctx.accounts.tail_highlight_page.as_mut().map(|tail| &mut tail.highlights),
+        )?;
+        event.highlight_count -= 1;
         Ok(())
     }
 
-    pub fn delete_event(ctx: Context<DeleteEvent>) -> Result<()> {
-        let event = &ctx.accounts.event;
-        let program_data = &mut ctx.accounts.program_data;
+    pub fn remove_highlight(ctx: Context<RemoveHighlight>, highlight_index: u32) -> Result<()> {
+        let user_data = &mut ctx.accounts.user_data;
+        let event = &mut ctx.accounts.event;
+        let highlight_page = &mut ctx.accounts.highlight_page;
         
+        let highlight = highlight_page.highlights
+            .get(highlight_index as usize)
+            .ok_or(EventError::InvalidImageIndex)?;
         require!(
-            event.owner == ctx.accounts.authority.key(),
-            EventError::UnauthorizedDeletion
+            highlight.uploader == user_data.owner,
+            EventError::ImageNotFound
         );
         
-        program_data.event_count = program_data.event_count.checked_sub(1)
-            .ok_or(EventError::EventCountUnderflow)?;
-            
+        // Remove the user copy as well so it is not left orphaned. It is
+        // already gone if the uploader was removed with `keep_highlights`
+        // and has since rejoined.
+        let tag = user_data.approved_image_position(&highlight.content_hash)
+            .map(|position| user_data.images.remove(position).tag)
+            .unwrap_or_default();
+        
+        emit!(HighlightRemoved {
+            event: event.key(),
+            uid: event.uid.clone(),
+            uploader: highlight.uploader,
+            url: highlight.url.clone(),
+            tag,
+            removed_by: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        check_tail_page(
+            highlight_page.page_no,
+            event.highlight_tail_page(),
+            ctx.accounts.tail_highlight_page.is_some(),
+        )?;
+        remove_paged(
+            &mut highlight_page.highlights,
+            highlight_index as usize,
+            ctx.accounts.tail_highlight_page.as_mut().map(|tail| &mut tail.highlights),
+        )?;
+        event.highlight_count -= 1;
+        Ok(())
+    }
+
+    /// Removes a highlight whose uploader has left the event with
+    /// `keep_highlights`, proven by their `UserData` no longer existing.
+    pub fn remove_departed_highlight(
+        ctx: Context<RemoveDepartedHighlight>,
+        highlight_index: u32,
+        uploader: Pubkey,
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        let highlight_page = &mut ctx.accounts.highlight_page;
+        
+        let highlight = highlight_page.highlights
+            .get(highlight_index as usize)
+            .ok_or(EventError::InvalidImageIndex)?;
+        require!(highlight.uploader == uploader, EventError::ImageNotFound);
+        
+        check_tail_page(
+            highlight_page.page_no,
+            event.highlight_tail_page(),
+            ctx.accounts.tail_highlight_page.is_some(),
+        )?;
+        let highlight = remove_paged(
+            &mut highlight_page.highlights,
+            highlight_index as usize,
+            ctx.accounts.tail_highlight_page.as_mut().map(|tail| &mut tail.highlights),
+        )?;
+        event.highlight_count -= 1;
+        
+        emit!(HighlightRemoved {
+            event: event.key(),
+            uid: event.uid.clone(),
+            uploader,
+            url: highlight.url,
+            tag: String::new(),
+            removed_by: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Takes the signer out of the event and refunds their `UserData` rent.
+    /// Unless `keep_highlights` is set, their gallery images are removed too;
+    /// pass the highlight pages from the first one holding their images up to
+    /// the tail page as remaining accounts.
+    pub fn leave_event<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LeaveEvent<'info>>,
+        keep_highlights: bool,
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        let authority = ctx.accounts.authority.key();
+        check_refund_claimed(event, &ctx.accounts.user_data)?;
+        
+        remove_attendee_entry(
+            event,
+            &mut ctx.accounts.attendee_page,
+            ctx.accounts.tail_attendee_page.as_deref_mut(),
+            &authority,
+        )?;
+        
+        let mut highlights_removed = 0;
+        if !keep_highlights {
+            // Leaving an archived album is fine; taking photos out of it is not
+            require!(event.status != EventStatus::Archived, EventError::EventArchived);
+            highlights_removed = purge_highlights(event, ctx.remaining_accounts, &authority)?;
+            require!(
+                highlights_removed == ctx.accounts.user_data.approved_image_count(),
+                EventError::HighlightNotFound
+            );
+        }
+        
+        emit!(AttendeeLeft {
+            event: event.key(),
+            uid: event.uid.clone(),
+            attendee: authority,
+            attendee_count: event.attendee_count,
+            highlights_removed,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        // UserData will be closed automatically due to the close constraint
+        Ok(())
+    }
+
+    /// Kicks an attendee out and refunds their `UserData` rent to them.
+    /// Unless `keep_highlights` is set their gallery images are purged too,
+    /// given the highlight pages as for `leave_event`. In large galleries
+    /// that may not fit in one transaction; keep them instead and take them
+    /// down one at a time with `remove_departed_highlight`. Pair with
+    /// `ban_member` to stop them rejoining.
+    pub fn remove_attendee<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RemoveAttendee<'info>>,
+        keep_highlights: bool,
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        let attendee = ctx.accounts.attendee.key();
+        check_refund_claimed(event, &ctx.accounts.user_data)?;
+        
+        remove_attendee_entry(
+            event,
+            &mut ctx.accounts.attendee_page,
+            ctx.accounts.tail_attendee_page.as_deref_mut(),
+            &attendee,
+        )?;
+        let mut highlights_removed = 0;
+        if !keep_highlights {
+            highlights_removed = purge_highlights(event, ctx.remaining_accounts, &attendee)?;
+            require!(
+                highlights_removed == ctx.accounts.user_data.approved_image_count(),
+                EventError::HighlightNotFound
+            );
+        }
+        
+        emit!(AttendeeRemoved {
+            event: event.key(),
+            uid: event.uid.clone(),
+            attendee,
+            attendee_count: event.attendee_count,
+            highlights_removed,
+            removed_by: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        // UserData will be closed automatically due to the close constraint
+        Ok(())
+    }
+
+    /// Stops `member` from joining again; revoke any role they hold first. If
+    /// they are joined right now they are removed as by `remove_attendee`:
+    /// pass their attendee page and wallet, and unless `keep_highlights` is
+    /// set the highlight pages as remaining accounts.
+    pub fn ban_member<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BanMember<'info>>,
+        member: Pubkey,
+        keep_highlights: bool,
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        require!(member != event.owner, EventError::CannotRemoveOwner);
+        let now = Clock::get()?.unix_timestamp;
+        
+        let ban = &mut ctx.accounts.ban;
+        ban.event = event.key();
+        ban.member = member;
+        ban.banned_by = ctx.accounts.authority.key();
+        ban.bump = ctx.bumps.ban;
+        event.ban_count += 1;
+        
+        emit!(MemberBanned {
+            event: ban.event,
+            uid: event.uid.clone(),
+            member,
+            banned_by: ban.banned_by,
+            timestamp: now,
+        });
+        
+        let user_data_info = ctx.accounts.user_data.to_account_info();
+        if user_data_info.data_is_empty() {
+            return Ok(());
+        }
+        
+        // Still joined: take them out so the ban covers their uploads too
+        let user_data = UserData::try_deserialize(&mut &user_data_info.try_borrow_data()?[..])?;
+        let (Some(attendee_page), Some(attendee)) = (
+            ctx.accounts.attendee_page.as_deref_mut(),
+            ctx.accounts.attendee.as_ref(),
+        ) else {
+            return err!(EventError::AttendeeAccountsRequired);
+        };
+        require!(event.status != EventStatus::Archived, EventError::EventArchived);
+        check_refund_claimed(event, &user_data)?;
+        
+        remove_attendee_entry(
+            event,
+            attendee_page,
+            ctx.accounts.tail_attendee_page.as_deref_mut(),
+            &member,
+        )?;
+        let mut highlights_removed = 0;
+        if !keep_highlights {
+            highlights_removed = purge_highlights(event, ctx.remaining_accounts, &member)?;
+            require!(
+                highlights_removed == user_data.approved_image_count(),
+                EventError::HighlightNotFound
+            );
+        }
+        
+        close_program_account(&user_data_info, &attendee.to_account_info())?;
+        
+        emit!(AttendeeRemoved {
+            event: event.key(),
+            uid: event.uid.clone(),
+            attendee: member,
+            attendee_count: event.attendee_count,
+            highlights_removed,
+            removed_by: ctx.accounts.authority.key(),
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    pub fn unban_member(ctx: Context<UnbanMember>) -> Result<()> {
+        ctx.accounts.event.ban_count -= 1;
+        
+        emit!(MemberUnbanned {
+            event: ctx.accounts.event.key(),
+            uid: ctx.accounts.event.uid.clone(),
+            member: ctx.accounts.ban.member,
+            unbanned_by: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
         // Account will be closed automatically due to the close constraint
         Ok(())
     }
-    // Fetch all events
-    pub fn get_all_events(ctx: Context<GetAllEvents>) -> Result<Vec<Event>> {
+
+    /// Reschedules the event. Once proceeds have been withdrawn `ends_at` is
+    /// fixed, since moving it would reopen joins that can no longer be refunded.
+    pub fn set_event_schedule(
+        ctx: Context<SetEventSchedule>,
+        starts_at: i64,
+        ends_at: i64,
+        uploads_close_at: i64,
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        require!(
+            ends_at == event.ends_at || !ctx.accounts.vault.proceeds_withdrawn(),
+            EventError::ProceedsAlreadyWithdrawn
+        );
+        event.set_schedule(starts_at, ends_at, uploads_close_at)?;
+        
+        emit!(EventScheduleUpdated {
+            event: event.key(),
+            uid: event.uid.clone(),
+            starts_at,
+            ends_at,
+            uploads_close_at,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn update_event(
+        ctx: Context<UpdateEvent>,
+        name: Option<String>,
+        banner: Option<String>,
+        max_attendees: Option<u32>,
+        max_images_per_user: Option<u32>,
+        entry_fee_lamports: Option<u64>,
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        
+        if let Some(name) = name {
+            require!(name.len() <= MAX_NAME_LEN, EventError::NameTooLong);
+            event.name = name;
+        }
+        if let Some(banner) = banner {
+            require!(banner.len() <= MAX_BANNER_LEN, EventError::BannerTooLong);
+            event.banner = banner;
+        }
+        if let Some(max_attendees) = max_attendees {
+            // The cap cannot drop below the people already in the event
+            require!(
+                max_attendees > 0 && max_attendees >= event.attendee_count,
+                EventError::InvalidEventLimit
+            );
+            event.max_attendees = max_attendees;
+        }
+        if let Some(max_images_per_user) = max_images_per_user {
+            require!(max_images_per_user > 0, EventError::InvalidEventLimit);
+            event.max_images_per_user = max_images_per_user;
+        }
+        if let Some(entry_fee_lamports) = entry_fee_lamports {
+            event.entry_fee_lamports = entry_fee_lamports;
+        }
+        
+        emit!(EventUpdated {
+            event: event.key(),
+            uid: event.uid.clone(),
+            name: event.name.clone(),
+            banner: event.banner.clone(),
+            max_attendees: event.max_attendees,
+            max_images_per_user: event.max_images_per_user,
+            entry_fee_lamports: event.entry_fee_lamports,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Sends everything in the event vault above its rent reserve to the
+    /// owner. Proceeds stay in escrow until the event has ended or been
+    /// closed, so a cancellation can still refund everyone.
+    pub fn withdraw_proceeds(ctx: Context<WithdrawProceeds>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(ctx.accounts.event.proceeds_released(now), EventError::ProceedsLocked);
+        let vault = &mut ctx.accounts.vault;
+        let reserve = Rent::get()?.minimum_balance(EventVault::SPACE);
+        let amount = vault.get_lamports().saturating_sub(reserve);
+        require!(amount > 0, EventError::NothingToWithdraw);
+        
+        vault.sub_lamports(amount)?;
+        ctx.accounts.authority.add_lamports(amount)?;
+        vault.withdrawn += amount;
+        
+        emit!(ProceedsWithdrawn {
+            event: ctx.accounts.event.key(),
+            uid: ctx.accounts.event.uid.clone(),
+            owner: ctx.accounts.authority.key(),
+            amount,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Charges `amount` of `fee_mint` to join, on top of any SOL entry fee,
+    /// and creates the event's associated token account for it. Works with
+    /// both the Token and Token-2022 programs. The mint cannot change once
+    /// anyone has paid in it.
+    pub fn set_token_fee(ctx: Context<SetTokenFee>, amount: u64) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        let fee_mint = ctx.accounts.fee_mint.key();
+        
+        require!(
+            event.fee_mint.is_none() || event.fee_mint == Some(fee_mint) || ctx.accounts.vault.token_collected == 0,
+            EventError::FeeMintLocked
+        );
+        event.fee_mint = Some(fee_mint);
+        event.token_entry_fee = amount;
+        
+        emit!(TokenFeeUpdated {
+            event: event.key(),
+            uid: event.uid.clone(),
+            mint: fee_mint,
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Sends the event's whole token balance to a token account of the owner's choosing.
+    pub fn withdraw_token_proceeds(ctx: Context<WithdrawTokenProceeds>) -> Result<()> {
         let event = &ctx.accounts.event;
-        Ok(vec![event.clone().into_inner()]) // Extract the inner Event struct
+        let now = Clock::get()?.unix_timestamp;
+        require!(event.proceeds_released(now), EventError::ProceedsLocked);
+        let amount = ctx.accounts.event_token_account.amount;
+        require!(amount > 0, EventError::NothingToWithdraw);
+        
+        transfer_event_tokens(
+            event,
+            &ctx.accounts.token_program,
+            &ctx.accounts.fee_mint,
+            &ctx.accounts.event_token_account,
+            ctx.accounts.destination.to_account_info(),
+            amount,
+        )?;
+        ctx.accounts.vault.token_withdrawn += amount;
+        
+        emit!(TokenProceedsWithdrawn {
+            event: event.key(),
+            uid: event.uid.clone(),
+            owner: ctx.accounts.authority.key(),
+            mint: ctx.accounts.fee_mint.key(),
+            amount,
+            timestamp: now,
+        });
+        Ok(())
     }
 
-    // Fetch all images by event uploaded by the user
-    pub fn get_user_images_by_event(ctx: Context<GetUserImagesByEvent>) -> Result<Vec<UploadedImage>> {
+    /// Hands an attendee of a cancelled event back what they paid (SOL
+    /// entry fee minus the protocol share, and any token fee) and takes them
+    /// off the attendee list, closing their `UserData`. Pass the fee mint,
+    /// token accounts and token program if they paid a token fee.
+    pub fn claim_refund(ctx: Context<ClaimRefund>) -> Result<()> {
+        let event = &mut ctx.accounts.event;
         let user_data = &ctx.accounts.user_data;
+        let vault = &mut ctx.accounts.vault;
+        let authority = ctx.accounts.authority.key();
+        let lamports = user_data.entry_fee_paid;
+        let tokens = user_data.token_fee_paid;
+        require!(lamports > 0 || tokens > 0, EventError::NothingToRefund);
+        
+        remove_attendee_entry(
+            event,
+            &mut ctx.accounts.attendee_page,
+            ctx.accounts.tail_attendee_page.as_deref_mut(),
+            &authority,
+        )?;
+        
+        if lamports > 0 {
+            let reserve = Rent::get()?.minimum_balance(EventVault::SPACE);
+            require!(
+                vault.get_lamports().saturating_sub(reserve) >= lamports,
+                EventError::RefundUnavailable
+            );
+            vault.sub_lamports(lamports)?;
+            ctx.accounts.authority.add_lamports(lamports)?;
+            vault.refunded += lamports;
+        }
+        
+        if tokens > 0 {
+            let (Some(fee_mint), Some(event_token_account), Some(destination), Some(token_program)) = (
+                ctx.accounts.fee_mint.as_ref(),
+                ctx.accounts.event_token_account.as_ref(),
+                ctx.accounts.destination.as_ref(),
+                ctx.accounts.token_program.as_ref(),
+            ) else {
+                return err!(EventError::TokenAccountsRequired);
+            };
+            require!(event.fee_mint == Some(fee_mint.key()), EventError::FeeMintMismatch);
+            require_keys_eq!(
+                event_token_account.key(),
+                get_associated_token_address_with_program_id(&event.key(), &fee_mint.key(), &token_program.key()),
+                EventError::FeeMintMismatch
+            );
+            require!(event_token_account.amount >= tokens, EventError::RefundUnavailable);
+            transfer_event_tokens(
+                event,
+                token_program,
+                fee_mint,
+                event_token_account,
+                destination.to_account_info(),
+                tokens,
+            )?;
+            vault.token_refunded += tokens;
+        }
+        
+        emit!(RefundClaimed {
+            event: event.key(),
+            uid: event.uid.clone(),
+            attendee: authority,
+            lamports,
+            tokens,
+            attendee_count: event.attendee_count,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        // UserData will be closed automatically due to the close constraint
+        Ok(())
+    }
+
+    /// Mints the caller an attendance badge: a Token-2022 NFT whose on-mint
+    /// metadata carries the event's name, banner (as the uri) and uid. The
+    /// event PDA is the mint and metadata update authority, and the mint is
+    /// a PDA of the caller's `UserData`, so each attendance gets one badge.
+    pub fn claim_badge(ctx: Context<ClaimBadge>) -> Result<()> {
         let event = &ctx.accounts.event;
+        
+        write_mint_metadata(
+            event,
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            &ctx.accounts.authority,
+            ctx.accounts.badge_mint.to_account_info(),
+            MintMetadata {
+                name: event.name.clone(),
+                symbol: BADGE_SYMBOL.to_string(),
+                uri: event.banner.clone(),
+                fields: vec![(BADGE_UID_KEY.to_string(), event.uid.clone())],
+            },
+        )?;
+        mint_one(
+            event,
+            &ctx.accounts.token_program,
+            ctx.accounts.badge_mint.to_account_info(),
+            ctx.accounts.badge_account.to_account_info(),
+        )?;
+        
+        emit!(BadgeClaimed {
+            event: event.key(),
+            uid: event.uid.clone(),
+            attendee: ctx.accounts.authority.key(),
+            mint: ctx.accounts.badge_mint.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
 
-        let user_images: Vec<UploadedImage> = user_data.images
-            .iter()
-            .filter(|image| event.highlight_images.contains(&image.url))
-            .cloned()
-            .collect();
+    /// Opts an event into photo minting by creating its collection mint, a
+    /// Token-2022 group that every minted photo joins, and fixes the royalty
+    /// every minted photo advertises: `royalty_bps` of each sale,
+    /// `uploader_share` percent of it to the uploader and the rest to the
+    /// event owner. The royalty is advisory; nothing on chain pays it out.
+    pub fn create_photo_collection(
+        ctx: Context<CreatePhotoCollection>,
+        royalty_bps: u16,
+        uploader_share: u8,
+    ) -> Result<()> {
+        require!(
+            royalty_bps <= BPS_DENOMINATOR && uploader_share <= 100,
+            EventError::InvalidRoyalty
+        );
+        let event = &mut ctx.accounts.event;
+        event.photo_royalty_bps = royalty_bps;
+        event.uploader_royalty_share = uploader_share;
+        
+        let seeds: &[&[u8]] = &[EVENT_SEED, event.uid.as_bytes(), &[event.bump]];
+        let collection_mint = ctx.accounts.collection_mint.to_account_info();
+        top_up_rent(&ctx.accounts.system_program, &ctx.accounts.authority, &collection_mint, TOKEN_GROUP_LEN)?;
+        token_group_initialize(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TokenGroupInitialize {
+                    token_program_id: ctx.accounts.token_program.to_account_info(),
+                    group: collection_mint.clone(),
+                    mint: collection_mint,
+                    mint_authority: event.to_account_info(),
+                },
+                &[seeds],
+            ),
+            Some(event.key()),
+            u32::MAX,
+        )?;
+        
+        write_mint_metadata(
+            event,
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            &ctx.accounts.authority,
+            ctx.accounts.collection_mint.to_account_info(),
+            MintMetadata {
+                name: event.name.clone(),
+                symbol: PHOTO_SYMBOL.to_string(),
+                uri: event.banner.clone(),
+                fields: vec![(BADGE_UID_KEY.to_string(), event.uid.clone())],
+            },
+        )?;
+        
+        emit!(PhotoCollectionCreated {
+            event: event.key(),
+            uid: event.uid.clone(),
+            collection: ctx.accounts.collection_mint.key(),
+            royalty_bps,
+            uploader_share,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
 
-        Ok(user_images)
+    /// Turns one of the caller's approved images into a Token-2022 NFT they
+    /// own, added as a member of the event's collection group and tagged with
+    /// its advisory royalty split.
+    pub fn mint_image(ctx: Context<MintImage>, image_index: u32) -> Result<()> {
+        let event = &ctx.accounts.event;
+        let image = ctx.accounts.user_data.images
+            .get_mut(image_index as usize)
+            .ok_or(EventError::InvalidImageIndex)?;
+        require!(image.status == ImageStatus::Approved, EventError::ImageNotApproved);
+        require!(!image.minted, EventError::ImageAlreadyMinted);
+        image.minted = true;
+        
+        let mut fields = vec![
+            (BADGE_UID_KEY.to_string(), event.uid.clone()),
+            (COLLECTION_KEY.to_string(), ctx.accounts.collection_mint.key().to_string()),
+            (ROYALTY_BPS_KEY.to_string(), event.photo_royalty_bps.to_string()),
+        ];
+        if image.uploader == event.owner {
+            fields.push((event.owner.to_string(), 100.to_string()));
+        } else {
+            let uploader_share = event.uploader_royalty_share;
+            fields.push((image.uploader.to_string(), uploader_share.to_string()));
+            fields.push((event.owner.to_string(), (100 - uploader_share).to_string()));
+        }
+        fields.push((CONTENT_HASH_KEY.to_string(), to_hex(&image.content_hash)));
+        let url = image.storage.uri();
+        
+        let seeds: &[&[u8]] = &[EVENT_SEED, event.uid.as_bytes(), &[event.bump]];
+        let image_mint = ctx.accounts.image_mint.to_account_info();
+        top_up_rent(&ctx.accounts.system_program, &ctx.accounts.authority, &image_mint, TOKEN_GROUP_MEMBER_LEN)?;
+        token_member_initialize(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TokenMemberInitialize {
+                token_program_id: ctx.accounts.token_program.to_account_info(),
+                member: image_mint.clone(),
+                member_mint: image_mint,
+                member_mint_authority: event.to_account_info(),
+                group: ctx.accounts.collection_mint.to_account_info(),
+                group_update_authority: event.to_account_info(),
+            },
+            &[seeds],
+        ))?;
+        
+        write_mint_metadata(
+            event,
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            &ctx.accounts.authority,
+            ctx.accounts.image_mint.to_account_info(),
+            MintMetadata {
+                name: event.name.clone(),
+                symbol: PHOTO_SYMBOL.to_string(),
+                uri: url.clone(),
+                fields,
+            },
+        )?;
+        mint_one(
+            event,
+            &ctx.accounts.token_program,
+            ctx.accounts.image_mint.to_account_info(),
+            ctx.accounts.image_account.to_account_info(),
+        )?;
+        
+        emit!(ImageMinted {
+            event: event.key(),
+            uid: event.uid.clone(),
+            uploader: ctx.accounts.authority.key(),
+            url,
+            mint: ctx.accounts.image_mint.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct GetAllEvents<'info> {
-    #[account(mut)]
-    pub program_data: Account<'info, ProgramData>,
-    #[account(mut)]
-    pub event: Account<'info, Event>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-}
+    /// Switches the event to storing new images as leaves of a concurrent
+    /// merkle tree instead of in `UserData`, so uploads no longer cost rent.
+    /// The tree holds up to `2^max_depth` images and remembers the last
+    /// `max_buffer_size` roots, so proofs built against a slightly stale
+    /// root still verify while others keep appending. Images already in
+    /// `UserData` stay there.
+    pub fn create_image_tree(
+        ctx: Context<CreateImageTree>,
+        max_depth: u8,
+        max_buffer_size: u8,
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        let image_tree = &mut ctx.accounts.image_tree;
+        
+        image_tree.event = event.key();
+        image_tree.init(max_depth, max_buffer_size)?;
+        image_tree.bump = ctx.bumps.image_tree;
+        event.image_storage = ImageStorage::Tree;
+        
+        emit!(ImageTreeCreated {
+            event: event.key(),
+            uid: event.uid.clone(),
+            image_tree: image_tree.key(),
+            max_depth,
+            max_buffer_size,
+            root: image_tree.root(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
 
-#[derive(Accounts)]
+    /// Tree-mode counterpart of `upload_image_with_tag`. Only the leaf hash
+    /// is kept on chain; indexers rebuild the tree from `ImageAppended`.
+    /// Leaves cannot be moderated, so tree images never reach the highlight
+    /// gallery.
+    pub fn append_image(
+        ctx: Context<AppendImage>,
+        storage: StorageRef,
+        tag: String,
+        content_hash: [u8; 32],
+    ) -> Result<()> {
+        let user_data = &mut ctx.accounts.user_data;
+        let event = &ctx.accounts.event;
+        let image_tree = &mut ctx.accounts.image_tree;
+        
+        storage.validate()?;
+        require!(content_hash != [0; 32], EventError::MissingContentHash);
+        require!(tag.len() <= MAX_TAG_LEN, EventError::TagTooLong);
+        let role = event.role_of(&ctx.accounts.authority.key(), ctx.accounts.member_role.as_deref());
+        require!(
+            user_data.images.len() + (user_data.tree_image_count as usize) < event.max_images_per_user as usize
+                || role.is_some_and(Role::bypasses_limits),
+            EventError::MaxImagesReached
+        );
+        
+        require!(event.status == EventStatus::Active, EventError::EventNotActive);
+        
+        let now = Clock::get()?.unix_timestamp;
+        require!(now >= event.starts_at, EventError::EventNotStarted);
+        require!(now < event.uploads_close_at, EventError::UploadWindowClosed);
+        
+        let uploader = ctx.accounts.authority.key();
+        let url = storage.uri();
+        let leaf_index = image_tree.append(image_leaf(&url, &tag, &uploader, &content_hash))?;
+        user_data.tree_image_count += 1;
+        
+        emit!(ImageAppended {
+            event: event.key(),
+            uid: event.uid.clone(),
+            uploader,
+            url,
+            tag,
+            content_hash,
+            leaf_index,
+            root: image_tree.root(),
+            sequence_number: image_tree.sequence_number,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Fails unless the image is leaf `leaf_index` of the event's tree,
+    /// proven against the current root or one still in the changelog.
+    pub fn verify_image(
+        ctx: Context<VerifyImage>,
+        leaf_index: u32,
+        storage: StorageRef,
+        tag: String,
+        uploader: Pubkey,
+        content_hash: [u8; 32],
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let leaf = image_leaf(&storage.uri(), &tag, &uploader, &content_hash);
+        require!(
+            ctx.accounts.image_tree.verify(&leaf, leaf_index, &proof),
+            EventError::InvalidImageProof
+        );
+        Ok(())
+    }
+
+    /// Like `verify_image`, for callers that already hold the leaf hash.
+    pub fn verify_image_leaf(
+        ctx: Context<VerifyImage>,
+        leaf_index: u32,
+        leaf: [u8; 32],
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.image_tree.verify(&leaf, leaf_index, &proof),
+            EventError::InvalidImageProof
+        );
+        Ok(())
+    }
+
+    /// Switches who may join. Send this in the same transaction as
+    /// `create_event` to keep a private event closed from the start.
+    pub fn set_access_mode(
+        ctx: Context<UpdateEvent>,
+        access_mode: AccessMode,
+        allowlist_root: [u8; 32],
+    ) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        event.access_mode = access_mode;
+        event.allowlist_root = allowlist_root;
+        
+        emit!(AccessModeUpdated {
+            event: event.key(),
+            uid: event.uid.clone(),
+            access_mode,
+            allowlist_root,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn grant_role(ctx: Context<GrantRole>, member: Pubkey, role: Role) -> Result<()> {
+        // Ownership is tied to `Event.owner` and cannot be handed out as a role
+        require!(role != Role::Owner, EventError::InvalidRole);
+        require!(member != ctx.accounts.event.owner, EventError::InvalidRole);
+        
+        let member_role = &mut ctx.accounts.member_role;
+        // Re-granting replaces the role rather than adding another one
+        if member_role.event == Pubkey::default() {
+            ctx.accounts.event.role_count += 1;
+        }
+        member_role.event = ctx.accounts.event.key();
+        member_role.member = member;
+        member_role.role = role;
+        member_role.bump = ctx.bumps.member_role;
+        
+        emit!(RoleGranted {
+            event: member_role.event,
+            uid: ctx.accounts.event.uid.clone(),
+            member,
+            role,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn revoke_role(ctx: Context<RevokeRole>) -> Result<()> {
+        let member_role = &ctx.accounts.member_role;
+        ctx.accounts.event.role_count -= 1;
+        
+        emit!(RoleRevoked {
+            event: member_role.event,
+            uid: ctx.accounts.event.uid.clone(),
+            member: member_role.member,
+            role: member_role.role,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        // Account will be closed automatically due to the close constraint
+        Ok(())
+    }
+
+    /// Moves an event through its lifecycle. Only `Active` events take joins
+    /// and uploads; attendees can still leave and reclaim their `UserData`
+    /// rent in any status. `Archived` is final: the album can no longer be
+    /// edited or deleted, so leavers must keep their highlights. A closed
+    /// event cannot reopen once proceeds have been withdrawn.
+    pub fn set_event_status(ctx: Context<SetEventStatus>, status: EventStatus) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        let program_data = &mut ctx.accounts.program_data;
+        let from = event.status;
+        
+        require!(from != EventStatus::Archived, EventError::EventArchived);
+        require!(from.can_become(status), EventError::InvalidStatusTransition);
+        require!(
+            status != EventStatus::Active || !ctx.accounts.vault.proceeds_withdrawn(),
+            EventError::ProceedsAlreadyWithdrawn
+        );
+        
+        if from == EventStatus::Active {
+            program_data.active_event_count = program_data.active_event_count.checked_sub(1)
+                .ok_or(EventError::EventCountUnderflow)?;
+        }
+        if status == EventStatus::Active {
+            program_data.active_event_count = program_data.active_event_count.checked_add(1)
+                .ok_or(EventError::EventCountOverflow)?;
+        }
+        event.status = status;
+        
+        emit!(EventStatusChanged {
+            event: event.key(),
+            uid: event.uid.clone(),
+            from,
+            to: status,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Calls an event off for good. Joins, uploads and withdrawals stop and
+    /// every paying attendee can `claim_refund`. Not possible once the owner
+    /// has withdrawn any proceeds.
+    pub fn cancel_event(ctx: Context<CancelEvent>) -> Result<()> {
+        let event = &mut ctx.accounts.event;
+        let program_data = &mut ctx.accounts.program_data;
+        let vault = &ctx.accounts.vault;
+        let from = event.status;
+        
+        require!(from != EventStatus::Archived, EventError::EventArchived);
+        require!(from != EventStatus::Cancelled, EventError::EventCancelled);
+        require!(!vault.proceeds_withdrawn(), EventError::ProceedsAlreadyWithdrawn);
+        
+        if from == EventStatus::Active {
+            program_data.active_event_count = program_data.active_event_count.checked_sub(1)
+                .ok_or(EventError::EventCountUnderflow)?;
+        }
+        event.status = EventStatus::Cancelled;
+        
+        emit!(EventStatusChanged {
+            event: event.key(),
+            uid: event.uid.clone(),
+            from,
+            to: EventStatus::Cancelled,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Deletes an event once every attendee but the owner is gone. The
+    /// owner's `UserData` is closed with it if they joined. Pass every
+    /// attendee page and then every highlight page, in page order, as
+    /// remaining accounts so they are closed too.
+    pub fn delete_event<'info>(ctx: Context<'_, '_, 'info, 'info, DeleteEvent<'info>>) -> Result<()> {
+        let event = &ctx.accounts.event;
+        let program_data = &mut ctx.accounts.program_data;
+        
+        require!(
+            event.owner == ctx.accounts.authority.key(),
+            EventError::UnauthorizedDeletion
+        );
+        require!(event.status != EventStatus::Archived, EventError::EventArchived);
+        // Deleting under live attendees would strand their UserData rent. The
+        // owner is counted once whether or not they joined.
+        require!(event.attendee_count <= 1, EventError::EventHasAttendees);
+        // A later event with the same uid would inherit these accounts
+        require!(
+            event.role_count == 0 && event.ban_count == 0,
+            EventError::EventHasRolesOrBans
+        );
+        // The event PDA signs for its token account, so it must be drained first
+        let vault = &ctx.accounts.vault;
+        require!(
+            vault.token_withdrawn + vault.token_refunded >= vault.token_collected,
+            EventError::TokenProceedsRemaining
+        );
+        // Closing the vault would hand unclaimed refunds to the owner
+        require!(
+            event.status != EventStatus::Cancelled
+                || vault.withdrawn + vault.refunded >= vault.collected,
+            EventError::RefundPending
+        );
+        
+        // A leftover attendee page 0 would block the uid for good, and a
+        // leftover highlight page would leak old photos into a new gallery
+        let attendee_pages = event.attendee_page_count() as usize;
+        let highlight_pages = event.highlight_page_count() as usize;
+        require!(
+            ctx.remaining_accounts.len() == attendee_pages + highlight_pages,
+            EventError::EventPagesRequired
+        );
+        let (attendee_pages, highlight_pages) = ctx.remaining_accounts.split_at(attendee_pages);
+        let authority = ctx.accounts.authority.to_account_info();
+        for (page_no, page) in attendee_pages.iter().enumerate() {
+            close_event_page(page, &AttendeePage::DISCRIMINATOR, &event.key(), page_no as u32, &authority)?;
+        }
+        for (page_no, page) in highlight_pages.iter().enumerate() {
+            close_event_page(page, &HighlightPage::DISCRIMINATOR, &event.key(), page_no as u32, &authority)?;
+        }
+        // Left open, it would let the owner upload to a recreated event unjoined
+        let owner_user_data = ctx.accounts.owner_user_data.to_account_info();
+        if !owner_user_data.data_is_empty() {
+            close_program_account(&owner_user_data, &ctx.accounts.authority.to_account_info())?;
+        }
+        
+        program_data.event_count = program_data.event_count.checked_sub(1)
+            .ok_or(EventError::EventCountUnderflow)?;
+        if event.status == EventStatus::Active {
+            program_data.active_event_count = program_data.active_event_count.checked_sub(1)
+                .ok_or(EventError::EventCountUnderflow)?;
+        }
+        
+        emit!(EventDeleted {
+            event: event.key(),
+            uid: event.uid.clone(),
+            owner: event.owner,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+            
+        // Account will be closed automatically due to the close constraint
+        Ok(())
+    }
+    // Fetch all events
+    pub fn get_all_events(ctx: Context<GetAllEvents>) -> Result<Vec<Event>> {
+        let event = &ctx.accounts.event;
+        Ok(vec![event.clone().into_inner()]) // Extract the inner Event struct
+    }
+
+    // Fetch all images by event uploaded by the user
+    pub fn get_user_images_by_event(ctx: Context<GetUserImagesByEvent>) -> Result<Vec<UploadedImage>> {
+        let user_data = &ctx.accounts.user_data;
+
+        // Deletions cascade both ways, so every approved image is also an event highlight
+        let user_images: Vec<UploadedImage> = user_data.images
+            .iter()
+            .filter(|image| image.status == ImageStatus::Approved)
+            .cloned()
+            .collect();
+
+        Ok(user_images)
+    }
+
+    // Fetch the approved photos the oracle matched to the user's selfie. Matches
+    // are not dropped when the photo is later deleted or removed, so clients
+    // should check each `content_hash` against the gallery before showing it.
+    pub fn get_photos_of_user(ctx: Context<GetUserImagesByEvent>) -> Result<Vec<Highlight>> {
+        Ok(ctx.accounts.user_data.appears_in.clone())
+    }
+
+    // Fetch one page of attendees; pages run from 0 to `Event::attendee_page_count`
+    pub fn get_attendees(ctx: Context<GetAttendeePage>) -> Result<Vec<Pubkey>> {
+        Ok(ctx.accounts.attendee_page.attendees.clone())
+    }
+
+    // Fetch one page of highlights; pages run from 0 to `Event::highlight_page_count`
+    pub fn get_highlights(ctx: Context<GetHighlightPage>) -> Result<Vec<Highlight>> {
+        Ok(ctx.accounts.highlight_page.highlights.clone())
+    }
+}
+
+/// Rejects a removal unless the tail page is supplied exactly when the entry
+/// lives on an earlier page.
+fn check_tail_page(page_no: u32, tail_page_no: u32, has_tail: bool) -> Result<()> {
+    require!(
+        (page_no == tail_page_no) != has_tail,
+        EventError::InvalidTailPage
+    );
+    Ok(())
+}
+
+/// Removes `index` from `page`, back-filling the hole with the last entry of
+/// the tail page so that every page but the last stays full.
+fn remove_paged<T>(page: &mut Vec<T>, index: usize, tail: Option<&mut Vec<T>>) -> Result<T> {
+    require!(index < page.len(), EventError::InvalidImageIndex);
+    match tail {
+        Some(tail) => {
+            let last = tail.pop().ok_or(EventError::InvalidTailPage)?;
+            Ok(std::mem::replace(&mut page[index], last))
+        }
+        None => Ok(page.swap_remove(index)),
+    }
+}
+
+/// Moves tokens out of the event's associated token account, signed by the event PDA.
+fn transfer_event_tokens<'info>(
+    event: &Account<'info, Event>,
+    token_program: &Interface<'info, TokenInterface>,
+    mint: &InterfaceAccount<'info, Mint>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    to: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let seeds: &[&[u8]] = &[EVENT_SEED, event.uid.as_bytes(), &[event.bump]];
+    transfer_checked(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            TransferChecked {
+                from: from.to_account_info(),
+                mint: mint.to_account_info(),
+                to,
+                authority: event.to_account_info(),
+            },
+            &[seeds],
+        ),
+        amount,
+        mint.decimals,
+    )
+}
+
+/// Token-2022 metadata written onto the badge and photo mints, which point
+/// their metadata at themselves.
+struct MintMetadata {
+    name: String,
+    symbol: String,
+    uri: String,
+    fields: Vec<(String, String)>,
+}
+
+impl MintMetadata {
+    /// Bytes Token-2022 appends to the mint for this metadata: the extension
+    /// type and length header plus the packed `TokenMetadata`.
+    fn len(&self) -> usize {
+        2 + 2 + // extension type, length
+            32 + 32 + // update_authority, mint
+            4 + self.name.len() +
+            4 + self.symbol.len() +
+            4 + self.uri.len() +
+            4 + self.fields.iter().map(|(key, value)| 4 + key.len() + 4 + value.len()).sum::<usize>()
+    }
+}
+
+/// Lowercase hex, for metadata values such as `CONTENT_HASH_KEY`.
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Writes `metadata` onto a mint whose mint authority is the event PDA,
+/// which also becomes its update authority. Token-2022 grows the mint to
+/// hold the metadata but leaves funding it to the caller, so `payer` tops
+/// it up first.
+fn write_mint_metadata<'info>(
+    event: &Account<'info, Event>,
+    token_program: &Program<'info, Token2022>,
+    system_program: &Program<'info, System>,
+    payer: &Signer<'info>,
+    mint: AccountInfo<'info>,
+    metadata: MintMetadata,
+) -> Result<()> {
+    let seeds: &[&[u8]] = &[EVENT_SEED, event.uid.as_bytes(), &[event.bump]];
+    
+    top_up_rent(system_program, payer, &mint, metadata.len())?;
+    
+    token_metadata_initialize(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            TokenMetadataInitialize {
+                token_program_id: token_program.to_account_info(),
+                mint: mint.clone(),
+                metadata: mint.clone(),
+                mint_authority: event.to_account_info(),
+                update_authority: event.to_account_info(),
+            },
+            &[seeds],
+        ),
+        metadata.name,
+        metadata.symbol,
+        metadata.uri,
+    )?;
+    for (key, value) in metadata.fields {
+        token_metadata_update_field(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                TokenMetadataUpdateField {
+                    token_program_id: token_program.to_account_info(),
+                    metadata: mint.clone(),
+                    update_authority: event.to_account_info(),
+                },
+                &[seeds],
+            ),
+            Field::Key(key),
+            value,
+        )?;
+    }
+    Ok(())
+}
+
+/// Tops `account` up to rent exemption at `extra_len` bytes beyond its current
+/// size, ahead of a Token-2022 instruction that grows it by that much.
+fn top_up_rent<'info>(
+    system_program: &Program<'info, System>,
+    payer: &Signer<'info>,
+    account: &AccountInfo<'info>,
+    extra_len: usize,
+) -> Result<()> {
+    let required = Rent::get()?.minimum_balance(account.data_len() + extra_len);
+    transfer_lamports(system_program, payer, account.clone(), required.saturating_sub(account.lamports()))
+}
+
+/// Mints the single token of an NFT whose mint authority is the event PDA.
+fn mint_one<'info>(
+    event: &Account<'info, Event>,
+    token_program: &Program<'info, Token2022>,
+    mint: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+) -> Result<()> {
+    let seeds: &[&[u8]] = &[EVENT_SEED, event.uid.as_bytes(), &[event.bump]];
+    mint_to(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            MintTo {
+                mint,
+                to,
+                authority: event.to_account_info(),
+            },
+            &[seeds],
+        ),
+        1,
+    )
+}
+
+/// Closes an account this program owns but did not load as an `Account`,
+/// sending its rent to `destination`.
+fn close_program_account(account: &AccountInfo, destination: &AccountInfo) -> Result<()> {
+    let rent = account.lamports();
+    account.sub_lamports(rent)?;
+    destination.add_lamports(rent)?;
+    account.assign(&System::id());
+    account.realloc(0, false)?;
+    Ok(())
+}
+
+/// Paying attendees of a cancelled event may only go through `claim_refund`,
+/// which closes their `UserData` once they have their money back.
+fn check_refund_claimed(event: &Event, user_data: &UserData) -> Result<()> {
+    require!(
+        event.status != EventStatus::Cancelled
+            || (user_data.entry_fee_paid == 0 && user_data.token_fee_paid == 0),
+        EventError::RefundPending
+    );
+    Ok(())
+}
+
+fn transfer_lamports<'info>(
+    system_program: &Program<'info, System>,
+    from: &Signer<'info>,
+    to: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    transfer(
+        CpiContext::new(
+            system_program.to_account_info(),
+            Transfer {
+                from: from.to_account_info(),
+                to,
+            },
+        ),
+        amount,
+    )
+}
+
+/// Closes one of `event`'s attendee or highlight pages, sending its rent to `authority`.
+fn close_event_page(
+    page: &AccountInfo,
+    discriminator: &[u8],
+    event: &Pubkey,
+    page_no: u32,
+    authority: &AccountInfo,
+) -> Result<()> {
+    require_keys_eq!(*page.owner, crate::ID, EventError::EventPagesRequired);
+    {
+        // Only the header is read, so a whole gallery fits in the heap
+        let data = page.try_borrow_data()?;
+        require!(
+            data.len() >= 8 + 32 + 4
+                && data[..8] == *discriminator
+                && data[8..40] == event.to_bytes()
+                && data[40..44] == page_no.to_le_bytes(),
+            EventError::EventPagesRequired
+        );
+    }
+    close_program_account(page, authority)
+}
+
+/// Takes `attendee` off `page`, back-filling from the tail attendee page.
+fn remove_attendee_entry(
+    event: &mut Event,
+    page: &mut AttendeePage,
+    tail: Option<&mut AttendeePage>,
+    attendee: &Pubkey,
+) -> Result<()> {
+    let position = page.attendees
+        .iter()
+        .position(|entry| entry == attendee)
+        .ok_or(EventError::NotJoined)?;
+    check_tail_page(page.page_no, event.attendee_tail_page(), tail.is_some())?;
+    remove_paged(&mut page.attendees, position, tail.map(|tail| &mut tail.attendees))?;
+    event.attendee_count -= 1;
+    Ok(())
+}
+
+/// Drops every highlight by `uploader` from `pages`, which must be the
+/// consecutive highlight pages ending at the tail, and re-packs the rest so
+/// that the gallery stays dense. Returns how many highlights were dropped.
+fn purge_highlights<'info>(
+    event: &mut Account<'info, Event>,
+    pages: &'info [AccountInfo<'info>],
+    uploader: &Pubkey,
+) -> Result<u32> {
+    let mut pages = pages
+        .iter()
+        .map(Account::<HighlightPage>::try_from)
+        .collect::<Result<Vec<_>>>()?;
+    let tail_page_no = event.highlight_tail_page();
+    for (i, page) in pages.iter().enumerate() {
+        require_keys_eq!(page.event, event.key(), EventError::EventMismatch);
+        require!(
+            page.page_no as usize + pages.len() - i == tail_page_no as usize + 1,
+            EventError::InvalidTailPage
+        );
+    }
+    
+    let highlights: Vec<Highlight> = pages
+        .iter_mut()
+        .flat_map(|page| std::mem::take(&mut page.highlights))
+        .collect();
+    let total = highlights.len();
+    let mut kept = highlights
+        .into_iter()
+        .filter(|highlight| highlight.uploader != *uploader)
+        .collect::<Vec<_>>()
+        .into_iter();
+    let removed = (total - kept.len()) as u32;
+    
+    for page in pages.iter_mut() {
+        page.highlights = kept.by_ref().take(HIGHLIGHTS_PER_PAGE as usize).collect();
+        page.exit(&crate::ID)?;
+    }
+    event.highlight_count -= removed;
+    Ok(removed)
+}
+
+/// Message the event owner signs to invite `invitee`.
+pub fn invite_message(event: &Pubkey, invitee: &Pubkey) -> Vec<u8> {
+    [INVITE_PREFIX, event.as_ref(), invitee.as_ref()].concat()
+}
+
+/// Requires the instruction right before this one to be an ed25519 program
+/// check of `signer`'s signature over `message`. The precompile has already
+/// verified the signature itself by the time we run.
+fn verify_invite(instructions: &AccountInfo, signer: &Pubkey, message: &[u8]) -> Result<()> {
+    let current = load_current_index_checked(instructions)? as usize;
+    require!(current > 0, EventError::InviteRequired);
+    let ix = load_instruction_at_checked(current - 1, instructions)?;
+    require!(ix.program_id == ed25519_program::ID, EventError::InviteRequired);
+    
+    // Header of a single-signature ed25519 instruction: count, padding, then
+    // seven u16 offsets
+    let data = &ix.data;
+    require!(data.len() >= 16 && data[0] == 1, EventError::InvalidInvite);
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let public_key_offset = read_u16(6) as usize;
+    let message_offset = read_u16(10) as usize;
+    let message_size = read_u16(12) as usize;
+    
+    // Every part must come from the ed25519 instruction itself
+    require!(
+        read_u16(4) == u16::MAX && read_u16(8) == u16::MAX && read_u16(14) == u16::MAX,
+        EventError::InvalidInvite
+    );
+    require!(
+        data.get(public_key_offset..public_key_offset + 32) == Some(signer.as_ref()),
+        EventError::InvalidInvite
+    );
+    require!(
+        data.get(message_offset..message_offset + message_size) == Some(message),
+        EventError::InvalidInvite
+    );
+    Ok(())
+}
+
+/// Leaf of the allowlist merkle tree for `member`.
+pub fn allowlist_leaf(member: &Pubkey) -> [u8; 32] {
+    hashv(&[&[0], member.as_ref()]).to_bytes()
+}
+
+/// Parent of two allowlist nodes. Children are sorted so proofs need no
+/// left/right flags.
+pub fn allowlist_node(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[&[1], left, right]).to_bytes()
+}
+
+pub fn verify_allowlist_proof(root: &[u8; 32], member: &Pubkey, proof: &[[u8; 32]]) -> bool {
+    let computed = proof.iter()
+        .fold(allowlist_leaf(member), |node, sibling| allowlist_node(&node, sibling));
+    computed == *root
+}
+
+/// Leaf of an event's image tree, over the rendered `StorageRef::uri`.
+/// Strings are length-prefixed so no two (url, tag) splits hash alike.
+pub fn image_leaf(url: &str, tag: &str, uploader: &Pubkey, content_hash: &[u8; 32]) -> [u8; 32] {
+    hashv(&[
+        &[0],
+        &(url.len() as u32).to_le_bytes(),
+        url.as_bytes(),
+        &(tag.len() as u32).to_le_bytes(),
+        tag.as_bytes(),
+        uploader.as_ref(),
+        content_hash,
+    ]).to_bytes()
+}
+
+/// Interior node of an event's image tree; unlike the allowlist, children
+/// stay in position order.
+pub fn image_tree_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    hashv(&[&[1], left, right]).to_bytes()
+}
+
+#[derive(Accounts)]
+pub struct GetAllEvents<'info> {
+    #[account(
+        mut,
+        seeds = [PROGRAM_DATA_SEED],
+        bump = program_data.bump
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(
+        mut,
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump
+    )]
+    pub event: Account<'info, Event>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
 pub struct GetUserImagesByEvent<'info> {
+    #[account(
+        mut,
+        seeds = [USER_SEED, user_data.event.as_ref(), user_data.owner.as_ref()],
+        bump = user_data.bump,
+        has_one = event @ EventError::EventMismatch,
+        constraint = user_data.owner == authority.key() @ EventError::UnauthorizedUser
+    )]
+    pub user_data: Account<'info, UserData>,
+    #[account(
+        mut,
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump
+    )]
+    pub event: Account<'info, Event>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetAttendeePage<'info> {
+    #[account(
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        seeds = [ATTENDEES_SEED, event.key().as_ref(), &attendee_page.page_no.to_le_bytes()],
+        bump = attendee_page.bump
+    )]
+    pub attendee_page: Account<'info, AttendeePage>,
+}
+
+#[derive(Accounts)]
+pub struct GetHighlightPage<'info> {
+    #[account(
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        seeds = [HIGHLIGHTS_SEED, event.key().as_ref(), &highlight_page.page_no.to_le_bytes()],
+        bump = highlight_page.bump
+    )]
+    pub highlight_page: Account<'info, HighlightPage>,
+}
+
+#[error_code]
+pub enum EventError {
+    #[msg("Event UID must be 32 characters or less")]
+    UidTooLong,
+    #[msg("Event name must be 50 characters or less")]
+    NameTooLong,
+    #[msg("URL must be 200 characters or less")]
+    UrlTooLong,
+    #[msg("Tag must be 50 characters or less")]
+    TagTooLong,
+    #[msg("User has already joined this event")]
+    AlreadyJoined,
+    #[msg("User must join event before uploading images")]
+    NotJoined,
+    #[msg("Invalid image index")]
+    InvalidImageIndex,
+    #[msg("Only event owner can delete the event")]
+    UnauthorizedDeletion,
+    #[msg("Event count overflow")]
+    EventCountOverflow,
+    #[msg("Event count underflow")]
+    EventCountUnderflow,
+    #[msg("Maximum number of attendees reached")]
+    MaxAttendeesReached,
+    #[msg("Maximum number of images reached")]
+    MaxImagesReached,
+    #[msg("Signer does not own this user data")]
+    UnauthorizedUser,
+    #[msg("User data does not belong to this event")]
+    EventMismatch,
+    #[msg("Image not found in user data")]
+    ImageNotFound,
+    #[msg("Only event owner can remove highlights")]
+    UnauthorizedHighlightRemoval,
+    #[msg("Event limits must be greater than zero")]
+    InvalidEventLimit,
+    #[msg("Image not found on highlight page")]
+    HighlightNotFound,
+    #[msg("Tail page must be passed only when removing from an earlier page")]
+    InvalidTailPage,
+    #[msg("Only the oracle can moderate images")]
+    UnauthorizedOracle,
+    #[msg("Image has already been moderated")]
+    ImageNotPending,
+    #[msg("Image has not been approved")]
+    ImageNotApproved,
+    #[msg("User has not set a selfie")]
+    SelfieNotSet,
+    #[msg("Face match has already been recorded")]
+    FaceMatchAlreadyRecorded,
+    #[msg("Only the program owner can update program data")]
+    UnauthorizedAdmin,
+    #[msg("Signer is not the pending owner")]
+    NotPendingOwner,
+    #[msg("This instruction is currently paused")]
+    InstructionPaused,
+    #[msg("Unknown pause flags")]
+    InvalidPauseFlags,
+    #[msg("Event has not started yet")]
+    EventNotStarted,
+    #[msg("Event has already ended")]
+    EventEnded,
+    #[msg("Upload window for this event has closed")]
+    UploadWindowClosed,
+    #[msg("Event must start before it ends and uploads cannot close before the end")]
+    InvalidEventSchedule,
+    #[msg("Only the event owner can update this event")]
+    UnauthorizedEventUpdate,
+    #[msg("Event banner must be 200 characters or less")]
+    BannerTooLong,
+    #[msg("The owner role cannot be granted")]
+    InvalidRole,
+    #[msg("Only the event owner can grant or revoke roles")]
+    UnauthorizedRoleChange,
+    #[msg("Joining this event requires an invite signed by the owner")]
+    InviteRequired,
+    #[msg("Invite was not signed by the event owner for this user")]
+    InvalidInvite,
+    #[msg("User is not on the event allowlist")]
+    NotOnAllowlist,
+    #[msg("The event owner cannot leave their own event")]
+    OwnerCannotLeave,
+    #[msg("Uploader has not left the event")]
+    UploaderStillJoined,
+    #[msg("The event owner cannot be removed or banned")]
+    CannotRemoveOwner,
+    #[msg("User is banned from this event")]
+    Banned,
+    #[msg("Only the event owner or a co-host can remove or ban attendees")]
+    UnauthorizedAttendeeRemoval,
+    #[msg("Event still has attendees; close it and let them leave first")]
+    EventHasAttendees,
+    #[msg("Event is archived")]
+    EventArchived,
+    #[msg("Event is not open for joins or uploads")]
+    EventNotActive,
+    #[msg("Event cannot move to that status")]
+    InvalidStatusTransition,
+    #[msg("Protocol fee cannot exceed 10000 basis points")]
+    InvalidProtocolFee,
+    #[msg("Only the event owner can withdraw proceeds")]
+    UnauthorizedWithdrawal,
+    #[msg("No proceeds to withdraw")]
+    NothingToWithdraw,
+    #[msg("Fee mint cannot change after attendees have paid in it")]
+    FeeMintLocked,
+    #[msg("This event charges a token fee; pass the mint, token accounts and token program")]
+    TokenAccountsRequired,
+    #[msg("Token accounts do not match the event's fee mint")]
+    FeeMintMismatch,
+    #[msg("Event has not been cancelled")]
+    EventNotCancelled,
+    #[msg("Event is cancelled")]
+    EventCancelled,
+    #[msg("Nothing to refund")]
+    NothingToRefund,
+    #[msg("Withdraw the event's token proceeds before deleting it")]
+    TokenProceedsRemaining,
+    #[msg("Event funds cannot cover this refund")]
+    RefundUnavailable,
+    #[msg("Royalty must be at most 10000 basis points with an uploader share of at most 100 percent")]
+    InvalidRoyalty,
+    #[msg("Image has already been minted")]
+    ImageAlreadyMinted,
+    #[msg("This event stores images in its merkle tree; use append_image")]
+    ImagesStoredInTree,
+    #[msg("Image tree depth or buffer size out of range")]
+    InvalidImageTreeConfig,
+    #[msg("Image tree is full")]
+    ImageTreeFull,
+    #[msg("Merkle proof does not match a recent image tree root")]
+    InvalidImageProof,
+    #[msg("URL must be an http(s) URL without whitespace")]
+    InvalidHttpUrl,
+    #[msg("Invalid IPFS CID")]
+    InvalidIpfsCid,
+    #[msg("Arweave transaction id must be 43 base64url characters")]
+    InvalidArweaveTxId,
+    #[msg("Shadow Drive path must be a relative file path")]
+    InvalidShadowDrivePath,
+    #[msg("Images require a sha256 content hash")]
+    MissingContentHash,
+    #[msg("Attendees of this cancelled event still have refunds to claim")]
+    RefundPending,
+    #[msg("Member is still joined; pass their attendee page and wallet to remove them")]
+    AttendeeAccountsRequired,
+    #[msg("Proceeds stay in escrow until the event ends or is closed")]
+    ProceedsLocked,
+    #[msg("Proceeds have been withdrawn, so the event can no longer be cancelled, reopened or have its end moved")]
+    ProceedsAlreadyWithdrawn,
+    #[msg("Only the oracle or an event moderator can remove face matches")]
+    UnauthorizedFaceMatchRemoval,
+    #[msg("Revoke every role and lift every ban before deleting the event")]
+    EventHasRolesOrBans,
+    #[msg("Pass every attendee page, then every highlight page, in page order")]
+    EventPagesRequired,
+    #[msg("Revoke the member's role before banning them")]
+    MemberHasRole,
+    #[msg("Members cannot lift their own ban")]
+    CannotUnbanSelf,
+}
+
+#[account]
+#[derive(Default)]
+pub struct ProgramData {
+    pub owner: Pubkey,
+    pub oracle: Pubkey,
+    /// Events that exist, whatever their status
+    pub event_count: u64,
+    pub active_event_count: u64,
+    pub pending_owner: Option<Pubkey>,
+    pub paused: u8,
+    pub protocol_fee_bps: u16,
+    pub bump: u8,
+}
+
+impl ProgramData {
+    pub const SPACE: usize = 8 + // discriminator
+        32 + // owner
+        32 + // oracle
+        8 + 8 + // event_count, active_event_count
+        1 + 32 + // pending_owner
+        1 + // paused
+        2 + // protocol_fee_bps
+        1; // bump
+
+    pub fn is_paused(&self, flag: u8) -> bool {
+        self.paused & flag != 0
+    }
+}
+
+#[event]
+pub struct ProgramDataUpdated {
+    pub owner: Pubkey,
+    pub oracle: Pubkey,
+    pub pending_owner: Option<Pubkey>,
+    pub paused: u8,
+    pub protocol_fee_bps: u16,
+}
+
+#[event]
+pub struct EventCreated {
+    pub event: Pubkey,
+    pub uid: String,
+    pub name: String,
+    pub banner: String,
+    pub owner: Pubkey,
+    pub max_attendees: u32,
+    pub max_images_per_user: u32,
+    pub starts_at: i64,
+    pub ends_at: i64,
+    pub uploads_close_at: i64,
+    pub status: EventStatus,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EventUpdated {
+    pub event: Pubkey,
+    pub uid: String,
+    pub name: String,
+    pub banner: String,
+    pub max_attendees: u32,
+    pub max_images_per_user: u32,
+    pub entry_fee_lamports: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProceedsWithdrawn {
+    pub event: Pubkey,
+    pub uid: String,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TokenFeeUpdated {
+    pub event: Pubkey,
+    pub uid: String,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TokenProceedsWithdrawn {
+    pub event: Pubkey,
+    pub uid: String,
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RefundClaimed {
+    pub event: Pubkey,
+    pub uid: String,
+    pub attendee: Pubkey,
+    pub lamports: u64,
+    pub tokens: u64,
+    pub attendee_count: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BadgeClaimed {
+    pub event: Pubkey,
+    pub uid: String,
+    pub attendee: Pubkey,
+    pub mint: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PhotoCollectionCreated {
+    pub event: Pubkey,
+    pub uid: String,
+    pub collection: Pubkey,
+    pub royalty_bps: u16,
+    pub uploader_share: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ImageMinted {
+    pub event: Pubkey,
+    pub uid: String,
+    pub uploader: Pubkey,
+    pub url: String,
+    pub mint: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ImageTreeCreated {
+    pub event: Pubkey,
+    pub uid: String,
+    pub image_tree: Pubkey,
+    pub max_depth: u8,
+    pub max_buffer_size: u8,
+    pub root: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ImageAppended {
+    pub event: Pubkey,
+    pub uid: String,
+    pub uploader: Pubkey,
+    pub url: String,
+    pub tag: String,
+    pub content_hash: [u8; 32],
+    pub leaf_index: u32,
+    pub root: [u8; 32],
+    pub sequence_number: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccessModeUpdated {
+    pub event: Pubkey,
+    pub uid: String,
+    pub access_mode: AccessMode,
+    pub allowlist_root: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RoleGranted {
+    pub event: Pubkey,
+    pub uid: String,
+    pub member: Pubkey,
+    pub role: Role,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RoleRevoked {
+    pub event: Pubkey,
+    pub uid: String,
+    pub member: Pubkey,
+    pub role: Role,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EventScheduleUpdated {
+    pub event: Pubkey,
+    pub uid: String,
+    pub starts_at: i64,
+    pub ends_at: i64,
+    pub uploads_close_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EventStatusChanged {
+    pub event: Pubkey,
+    pub uid: String,
+    pub from: EventStatus,
+    pub to: EventStatus,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EventDeleted {
+    pub event: Pubkey,
+    pub uid: String,
+    pub owner: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AttendeeJoined {
+    pub event: Pubkey,
+    pub uid: String,
+    pub attendee: Pubkey,
+    pub attendee_count: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AttendeeLeft {
+    pub event: Pubkey,
+    pub uid: String,
+    pub attendee: Pubkey,
+    pub attendee_count: u32,
+    pub highlights_removed: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AttendeeRemoved {
+    pub event: Pubkey,
+    pub uid: String,
+    pub attendee: Pubkey,
+    pub attendee_count: u32,
+    pub highlights_removed: u32,
+    pub removed_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MemberBanned {
+    pub event: Pubkey,
+    pub uid: String,
+    pub member: Pubkey,
+    pub banned_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MemberUnbanned {
+    pub event: Pubkey,
+    pub uid: String,
+    pub member: Pubkey,
+    pub unbanned_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ImageUploaded {
+    pub event: Pubkey,
+    pub uid: String,
+    pub uploader: Pubkey,
+    pub url: String,
+    pub content_hash: [u8; 32],
+    pub tag: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ImageApproved {
+    pub event: Pubkey,
+    pub uid: String,
+    pub uploader: Pubkey,
+    pub url: String,
+    pub tag: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ImageRejected {
+    pub event: Pubkey,
+    pub uid: String,
+    pub uploader: Pubkey,
+    pub url: String,
+    pub tag: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ImageDeleted {
+    pub event: Pubkey,
+    pub uid: String,
+    pub uploader: Pubkey,
+    pub url: String,
+    pub tag: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct HighlightRemoved {
+    pub event: Pubkey,
+    pub uid: String,
+    pub uploader: Pubkey,
+    pub url: String,
+    pub tag: String,
+    pub removed_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SelfieSet {
+    pub event: Pubkey,
+    pub uid: String,
+    pub user: Pubkey,
+    pub selfie: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FaceMatchAttested {
+    pub event: Pubkey,
+    pub uid: String,
+    pub uploader: Pubkey,
+    pub url: String,
+    pub subject: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FaceMatchRemoved {
+    pub event: Pubkey,
+    pub uid: String,
+    pub uploader: Pubkey,
+    pub url: String,
+    pub subject: Pubkey,
+    pub removed_by: Pubkey,
+    pub timestamp: i64,
+}
+
+impl From<&ProgramData> for ProgramDataUpdated {
+    fn from(program_data: &ProgramData) -> Self {
+        Self {
+            owner: program_data.owner,
+            oracle: program_data.oracle,
+            pending_owner: program_data.pending_owner,
+            paused: program_data.paused,
+            protocol_fee_bps: program_data.protocol_fee_bps,
+        }
+    }
+}
+
+#[account]
+#[derive(Default)]
+pub struct Event {
+    pub uid: String,
+    pub name: String,
+    pub banner: String,
+    pub owner: Pubkey,
+    pub attendee_count: u32,
+    pub highlight_count: u32,
+    /// Live `MemberRole` and `Ban` accounts. Their seeds only depend on the
+    /// event address, so they must all be closed before the uid is freed.
+    pub role_count: u32,
+    pub ban_count: u32,
+    pub max_attendees: u32,
+    pub max_images_per_user: u32,
+    pub starts_at: i64,
+    pub ends_at: i64,
+    pub uploads_close_at: i64,
+    pub access_mode: AccessMode,
+    pub allowlist_root: [u8; 32],
+    pub status: EventStatus,
+    pub entry_fee_lamports: u64,
+    /// Mint of the token entry fee, if the event charges one
+    pub fee_mint: Option<Pubkey>,
+    pub token_entry_fee: u64,
+    /// Royalty advertised on minted photos, set with the photo collection
+    pub photo_royalty_bps: u16,
+    /// Percent of photo royalties going to the uploader rather than the owner
+    pub uploader_royalty_share: u8,
+    pub image_storage: ImageStorage,
+    pub bump: u8,
+}
+
+impl Event {
+    /// Strings are allocated at their maximum length so they can be edited in place.
+    pub const SPACE: usize = 8 + // discriminator
+        4 + MAX_UID_LEN +
+        4 + MAX_NAME_LEN +
+        4 + MAX_BANNER_LEN +
+        32 + // owner
+        4 + 4 + // attendee_count, highlight_count
+        4 + 4 + // role_count, ban_count
+        4 + 4 + // max_attendees, max_images_per_user
+        8 + 8 + 8 + // starts_at, ends_at, uploads_close_at
+        1 + 32 + // access_mode, allowlist_root
+        1 + // status
+        8 + // entry_fee_lamports
+        1 + 32 + 8 + // fee_mint, token_entry_fee
+        2 + 1 + // photo_royalty_bps, uploader_royalty_share
+        1 + // image_storage
+        1; // bump
+
+    /// Role of `member`, given the `MemberRole` account at its role PDA if one exists.
+    pub fn role_of(&self, member: &Pubkey, member_role: Option<&MemberRole>) -> Option<Role> {
+        if *member == self.owner {
+            return Some(Role::Owner);
+        }
+        member_role
+            .filter(|member_role| member_role.member == *member)
+            .map(|member_role| member_role.role)
+    }
+
+    /// Whether entry fees may leave escrow: the event has ended, or the
+    /// owner has closed or archived it.
+    pub fn proceeds_released(&self, now: i64) -> bool {
+        matches!(self.status, EventStatus::Closed | EventStatus::Archived) || now >= self.ends_at
+    }
+
+    pub fn set_schedule(&mut self, starts_at: i64, ends_at: i64, uploads_close_at: i64) -> Result<()> {
+        require!(
+            starts_at < ends_at && ends_at <= uploads_close_at,
+            EventError::InvalidEventSchedule
+        );
+        self.starts_at = starts_at;
+        self.ends_at = ends_at;
+        self.uploads_close_at = uploads_close_at;
+        Ok(())
+    }
+
+    pub fn attendee_page_count(&self) -> u32 {
+        self.attendee_count.div_ceil(ATTENDEES_PER_PAGE)
+    }
+
+    /// Page the next attendee is appended to.
+    pub fn next_attendee_page(&self) -> u32 {
+        self.attendee_count / ATTENDEES_PER_PAGE
+    }
+
+    /// Last non-empty attendee page, used to back-fill removals.
+    pub fn attendee_tail_page(&self) -> u32 {
+        self.attendee_count.saturating_sub(1) / ATTENDEES_PER_PAGE
+    }
+
+    pub fn highlight_page_count(&self) -> u32 {
+        self.highlight_count.div_ceil(HIGHLIGHTS_PER_PAGE)
+    }
+
+    /// Page the next highlight is appended to.
+    pub fn next_highlight_page(&self) -> u32 {
+        self.highlight_count / HIGHLIGHTS_PER_PAGE
+    }
+
+    /// Last non-empty highlight page, used to back-fill removals.
+    pub fn highlight_tail_page(&self) -> u32 {
+        self.highlight_count.saturating_sub(1) / HIGHLIGHTS_PER_PAGE
+    }
+}
+
+/// Fixed-size chunk of an event's attendee list. Pages are kept dense: removals
+/// are back-filled from the tail page, so only the last page is partially full.
+#[account]
+#[derive(Default)]
+pub struct AttendeePage {
+    pub event: Pubkey,
+    pub page_no: u32,
+    pub attendees: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl AttendeePage {
+    pub const SPACE: usize = 8 + // discriminator
+        32 + // event
+        4 + // page_no
+        4 + 32 * ATTENDEES_PER_PAGE as usize + // attendees
+        1; // bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct Highlight {
+    /// Rendered `StorageRef::uri` of the image
+    pub url: String,
+    pub uploader: Pubkey,
+    pub content_hash: [u8; 32],
+}
+
+impl Highlight {
+    pub fn space(url: &str) -> usize {
+        4 + url.len() + 32 + 32
+    }
+}
+
+/// Fixed-size chunk of an event's highlight gallery, kept dense like `AttendeePage`.
+#[account]
+#[derive(Default)]
+pub struct HighlightPage {
+    pub event: Pubkey,
+    pub page_no: u32,
+    pub highlights: Vec<Highlight>,
+    pub bump: u8,
+}
+
+impl HighlightPage {
+    pub const SPACE: usize = 8 + // discriminator
+        32 + // event
+        4 + // page_no
+        4 + (4 + MAX_URL_LEN + 32 + 32) * HIGHLIGHTS_PER_PAGE as usize + // highlights
+        1; // bump
+
+    pub fn position(&self, content_hash: &[u8; 32], uploader: &Pubkey) -> Option<usize> {
+        self.highlights
+            .iter()
+            .position(|highlight| highlight.content_hash == *content_hash && highlight.uploader == *uploader)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EventStatus {
+    /// Being set up; not yet open to attendees
+    #[default]
+    Draft,
+    Active,
+    /// Over; no new joins or uploads, can still be reopened or deleted
+    Closed,
+    /// Frozen album: readable but never modified or deleted again
+    Archived,
+    /// Called off through `cancel_event`; attendees can reclaim what they paid
+    Cancelled,
+}
+
+impl EventStatus {
+    pub fn can_become(self, to: EventStatus) -> bool {
+        use EventStatus::*;
+        matches!(
+            (self, to),
+            (Draft, Active) | (Draft, Archived) |
+            (Active, Closed) | (Active, Archived) |
+            (Closed, Active) | (Closed, Archived)
+        )
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImageStorage {
+    /// Each image lives in its uploader's `UserData`
+    #[default]
+    Accounts,
+    /// Images are leaves of the event's `ImageTree`
+    Tree,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AccessMode {
+    #[default]
+    Public,
+    InviteOnly,
+    Allowlist,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Owner,
+    CoHost,
+    Moderator,
+    Photographer,
+}
+
+impl Role {
+    /// Owners and co-hosts may edit the event; only the owner may delete it.
+    pub fn can_edit_event(self) -> bool {
+        matches!(self, Role::Owner | Role::CoHost)
+    }
+
+    /// Owners and moderators may remove any image from the gallery.
+    pub fn can_moderate(self) -> bool {
+        matches!(self, Role::Owner | Role::Moderator)
+    }
+
+    /// Owners and co-hosts may remove and ban attendees.
+    pub fn can_manage_attendees(self) -> bool {
+        matches!(self, Role::Owner | Role::CoHost)
+    }
+
+    /// Photographers are exempt from the attendee and per-user image caps.
+    pub fn bypasses_limits(self) -> bool {
+        self == Role::Photographer
+    }
+}
+
+#[account]
+pub struct MemberRole {
+    pub event: Pubkey,
+    pub member: Pubkey,
+    pub role: Role,
+    pub bump: u8,
+}
+
+/// Holds an event's SOL entry fees until the owner withdraws them, and
+/// tallies the token fees held in the event's associated token account.
+#[account]
+pub struct EventVault {
+    pub event: Pubkey,
+    pub collected: u64,
+    pub withdrawn: u64,
+    pub refunded: u64,
+    pub token_collected: u64,
+    pub token_withdrawn: u64,
+    pub token_refunded: u64,
+    pub bump: u8,
+}
+
+impl EventVault {
+    pub const SPACE: usize = 8 + // discriminator
+        32 + // event
+        8 + 8 + 8 + // collected, withdrawn, refunded
+        8 + 8 + 8 + // token_collected, token_withdrawn, token_refunded
+        1; // bump
+
+    /// Whether the owner has taken out any SOL or token proceeds, after which
+    /// the event can no longer promise refunds.
+    pub fn proceeds_withdrawn(&self) -> bool {
+        self.withdrawn > 0 || self.token_withdrawn > 0
+    }
+}
+
+#[account]
+pub struct Ban {
+    pub event: Pubkey,
+    pub member: Pubkey,
+    pub banned_by: Pubkey,
+    pub bump: u8,
+}
+
+impl Ban {
+    pub const SPACE: usize = 8 + // discriminator
+        32 + 32 + 32 + // event, member, banned_by
+        1; // bump
+}
+
+/// An event's concurrent merkle tree of images. Appends only need the
+/// rightmost path, so uploaders never send proofs; the changelog ring of
+/// recent roots lets proofs built a few appends ago still verify.
+#[account]
+pub struct ImageTree {
+    pub event: Pubkey,
+    pub max_depth: u8,
+    pub max_buffer_size: u8,
+    /// Bumped on every append
+    pub sequence_number: u64,
+    pub leaf_count: u32,
+    /// Slot of `changelog` holding the current root
+    pub active_index: u32,
+    /// Last left-hand node written at each level
+    pub rightmost_path: Vec<[u8; 32]>,
+    pub changelog: Vec<ChangeLog>,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct ChangeLog {
+    pub root: [u8; 32],
+    /// Leaf appended to reach `root`
+    pub leaf_index: u32,
+}
+
+impl ImageTree {
+    pub fn space(max_depth: u8, max_buffer_size: u8) -> usize {
+        8 + // discriminator
+            32 + // event
+            1 + 1 + // max_depth, max_buffer_size
+            8 + 4 + 4 + // sequence_number, leaf_count, active_index
+            4 + 32 * max_depth as usize + // rightmost_path
+            4 + (32 + 4) * max_buffer_size as usize + // changelog
+            1 // bump
+    }
+
+    pub fn init(&mut self, max_depth: u8, max_buffer_size: u8) -> Result<()> {
+        require!(
+            (1..=MAX_IMAGE_TREE_DEPTH).contains(&max_depth)
+                && (1..=MAX_IMAGE_TREE_BUFFER_SIZE).contains(&max_buffer_size),
+            EventError::InvalidImageTreeConfig
+        );
+        let empty_root = (0..max_depth).fold([0; 32], |zero, _| image_tree_node(&zero, &zero));
+        self.max_depth = max_depth;
+        self.max_buffer_size = max_buffer_size;
+        self.sequence_number = 0;
+        self.leaf_count = 0;
+        self.active_index = 0;
+        self.rightmost_path = vec![[0; 32]; max_depth as usize];
+        self.changelog = vec![ChangeLog::default(); max_buffer_size as usize];
+        self.changelog[0].root = empty_root;
+        Ok(())
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.changelog[self.active_index as usize].root
+    }
+
+    /// Appends `leaf` and returns its index.
+    pub fn append(&mut self, leaf: [u8; 32]) -> Result<u32> {
+        let leaf_index = self.leaf_count;
+        require!((leaf_index as u64) < 1 << self.max_depth, EventError::ImageTreeFull);
+        
+        let mut node = leaf;
+        let mut zero = [0; 32];
+        for (level, rightmost) in self.rightmost_path.iter_mut().enumerate() {
+            if leaf_index >> level & 1 == 0 {
+                *rightmost = node;
+                node = image_tree_node(&node, &zero);
+            } else {
+                node = image_tree_node(rightmost, &node);
+            }
+            zero = image_tree_node(&zero, &zero);
+        }
+        
+        self.active_index = (self.active_index + 1) % self.max_buffer_size as u32;
+        self.changelog[self.active_index as usize] = ChangeLog { root: node, leaf_index };
+        self.sequence_number += 1;
+        self.leaf_count += 1;
+        Ok(leaf_index)
+    }
+
+    /// Whether `proof` takes `leaf` at `leaf_index` to any root still in the changelog.
+    pub fn verify(&self, leaf: &[u8; 32], leaf_index: u32, proof: &[[u8; 32]]) -> bool {
+        if proof.len() != self.max_depth as usize || leaf_index >= self.leaf_count {
+            return false;
+        }
+        let root = proof.iter().enumerate().fold(*leaf, |node, (level, sibling)| {
+            if leaf_index >> level & 1 == 0 {
+                image_tree_node(&node, sibling)
+            } else {
+                image_tree_node(sibling, &node)
+            }
+        });
+        // Only slots written since creation hold real roots
+        let recent = (self.sequence_number + 1).min(self.max_buffer_size as u64) as usize;
+        let size = self.max_buffer_size as usize;
+        (0..recent).any(|age| {
+            let slot = (self.active_index as usize + size - age) % size;
+            self.changelog[slot].root == root
+        })
+    }
+}
+
+impl MemberRole {
+    pub const SPACE: usize = 8 + // discriminator
+        32 + 32 + // event, member
+        1 + // role
+        1; // bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImageStatus {
+    #[default]
+    Pending,
+    Approved,
+    Rejected,
+}
+
+/// Where an image's bytes live. Content-addressed variants pin the file;
+/// `content_hash` on the image covers the rest.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum StorageRef {
+    /// Plain `http://` or `https://` URL
+    Http(String),
+    /// CIDv0 (`Qm...`) or base32 CIDv1 (`b...`)
+    Ipfs(String),
+    /// Arweave transaction id
+    Arweave(String),
+    /// File in a Shadow Drive storage account
+    ShadowDrive { account: Pubkey, path: String },
+}
+
+impl StorageRef {
+    pub fn space(&self) -> usize {
+        1 + match self {
+            StorageRef::Http(value) | StorageRef::Ipfs(value) | StorageRef::Arweave(value) => {
+                4 + value.len()
+            }
+            StorageRef::ShadowDrive { path, .. } => 32 + 4 + path.len(),
+        }
+    }
+
+    /// URI clients fetch the image from.
+    pub fn uri(&self) -> String {
+        match self {
+            StorageRef::Http(url) => url.clone(),
+            StorageRef::Ipfs(cid) => format!("ipfs://{}", cid),
+            StorageRef::Arweave(tx_id) => format!("ar://{}", tx_id),
+            StorageRef::ShadowDrive { account, path } => {
+                format!("{}{}/{}", SHADOW_DRIVE_URL_PREFIX, account, path)
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            StorageRef::Http(url) => {
+                let rest = url
+                    .strip_prefix("https://")
+                    .or_else(|| url.strip_prefix("http://"))
+                    .ok_or(EventError::InvalidHttpUrl)?;
+                require!(
+                    !rest.is_empty() && rest.bytes().all(|byte| byte.is_ascii_graphic()),
+                    EventError::InvalidHttpUrl
+                );
+            }
+            StorageRef::Ipfs(cid) => {
+                let v0 = cid.len() == 46
+                    && cid.starts_with("Qm")
+                    && cid.bytes().all(|byte| byte.is_ascii_alphanumeric() && !b"0OIl".contains(&byte));
+                let v1 = cid.len() > 1
+                    && cid.len() <= MAX_CID_LEN
+                    && cid.starts_with('b')
+                    && cid.bytes().all(|byte| matches!(byte, b'a'..=b'z' | b'2'..=b'7'));
+                require!(v0 || v1, EventError::InvalidIpfsCid);
+            }
+            StorageRef::Arweave(tx_id) => {
+                require!(
+                    tx_id.len() == ARWEAVE_TX_ID_LEN
+                        && tx_id
+                            .bytes()
+                            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_'),
+                    EventError::InvalidArweaveTxId
+                );
+            }
+            StorageRef::ShadowDrive { path, .. } => {
+                require!(
+                    !path.is_empty()
+                        && !path.starts_with('/')
+                        && path.split('/').all(|segment| !segment.is_empty() && segment != "..")
+                        && path.bytes().all(|byte| byte.is_ascii_graphic()),
+                    EventError::InvalidShadowDrivePath
+                );
+            }
+        }
+        require!(self.uri().len() <= MAX_URL_LEN, EventError::UrlTooLong);
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct UploadedImage {
+    pub storage: StorageRef,
+    /// sha256 of the image file
+    pub content_hash: [u8; 32],
+    pub tag: String,
+    pub uploader: Pubkey,
+    pub status: ImageStatus,
+    /// Whether the uploader has minted this image as an NFT
+    pub minted: bool,
+}
+
+impl UploadedImage {
+    pub fn space(storage: &StorageRef, tag: &str) -> usize {
+        storage.space() + 32 + 4 + tag.len() + 32 + 1 + 1
+    }
+}
+
+#[account]
+#[derive(Default)]
+pub struct UserData {
+    pub owner: Pubkey,
+    pub event: Pubkey,
+    pub uploader_selfie: String,
+    pub is_joined: bool,
+    pub images: Vec<UploadedImage>,
+    /// Approved photos the oracle attested this user appears in
+    pub appears_in: Vec<Highlight>,
+    /// Lamports paid into the event vault on join, refundable if the event is cancelled
+    pub entry_fee_paid: u64,
+    /// Token entry fee paid on join, refundable if the event is cancelled
+    pub token_fee_paid: u64,
+    /// Images appended to the event's image tree, counted against the per-user cap
+    pub tree_image_count: u32,
+    pub bump: u8,
+}
+
+impl UserData {
+    /// Size of a freshly joined user with an empty selfie, no images and no face matches.
+    pub const BASE_SPACE: usize = 8 + // discriminator
+        32 + // owner
+        32 + // event
+        4 + // uploader_selfie
+        1 + // is_joined
+        4 + // images
+        4 + // appears_in
+        8 + 8 + // entry_fee_paid, token_fee_paid
+        4 + // tree_image_count
+        1; // bump
+
+    /// Space taken by the image at `index`, or zero when out of range.
+    pub fn image_space(&self, index: usize) -> usize {
+        self.images
+            .get(index)
+            .map_or(0, |image| UploadedImage::space(&image.storage, &image.tag))
+    }
+
+    pub fn approved_image_count(&self) -> u32 {
+        self.images
+            .iter()
+            .filter(|image| image.status == ImageStatus::Approved)
+            .count() as u32
+    }
+
+    pub fn approved_image_position(&self, content_hash: &[u8; 32]) -> Option<usize> {
+        self.images
+            .iter()
+            .position(|image| image.content_hash == *content_hash && image.status == ImageStatus::Approved)
+    }
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = ProgramData::SPACE,
+        seeds = [PROGRAM_DATA_SEED],
+        bump
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(constraint = program.programdata_address()? == Some(program_upgrade_data.key()))]
+    pub program: Program<'info, crate::program::Eventsnap>,
+    /// Loader account of this program; only its upgrade authority may initialize
+    #[account(
+        constraint = program_upgrade_data.upgrade_authority_address == Some(owner.key())
+            @ EventError::UnauthorizedAdmin
+    )]
+    pub program_upgrade_data: Account<'info, anchor_lang::ProgramData>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProgramData<'info> {
+    #[account(
+        mut,
+        seeds = [PROGRAM_DATA_SEED],
+        bump = program_data.bump,
+        has_one = owner @ EventError::UnauthorizedAdmin
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptOwner<'info> {
+    #[account(
+        mut,
+        seeds = [PROGRAM_DATA_SEED],
+        bump = program_data.bump,
+        constraint = program_data.pending_owner == Some(new_owner.key()) @ EventError::NotPendingOwner
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    pub new_owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(uid: String)]
+pub struct CreateEvent<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = Event::SPACE,
+        seeds = [EVENT_SEED, uid.as_bytes()],
+        bump
+    )]
+    pub event: Account<'info, Event>,
+    // The owner is the first attendee
+    #[account(
+        init,
+        payer = authority,
+        space = AttendeePage::SPACE,
+        seeds = [ATTENDEES_SEED, event.key().as_ref(), &0u32.to_le_bytes()],
+        bump
+    )]
+    pub attendee_page: Account<'info, AttendeePage>,
+    #[account(
+        init,
+        payer = authority,
+        space = EventVault::SPACE,
+        seeds = [VAULT_SEED, event.key().as_ref()],
+        bump
+    )]
+    pub vault: Account<'info, EventVault>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_DATA_SEED],
+        bump = program_data.bump,
+        constraint = !program_data.is_paused(PAUSE_CREATE_EVENT) @ EventError::InstructionPaused
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct JoinEvent<'info> {
+    #[account(
+        seeds = [PROGRAM_DATA_SEED],
+        bump = program_data.bump,
+        constraint = !program_data.is_paused(PAUSE_JOIN_EVENT) @ EventError::InstructionPaused
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(
+        mut,
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = AttendeePage::SPACE,
+        seeds = [ATTENDEES_SEED, event.key().as_ref(), &event.next_attendee_page().to_le_bytes()],
+        bump
+    )]
+    pub attendee_page: Account<'info, AttendeePage>,
+    // `init_if_needed` so a second join reaches the `AlreadyJoined` check
+    // instead of failing inside the system program.
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = UserData::BASE_SPACE,
+        seeds = [USER_SEED, event.key().as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub user_data: Account<'info, UserData>,
+    #[account(
+        seeds = [ROLE_SEED, event.key().as_ref(), authority.key().as_ref()],
+        bump = member_role.bump
+    )]
+    pub member_role: Option<Account<'info, MemberRole>>,
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, event.key().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, EventVault>,
+    /// CHECK: receives the protocol share of the entry fee
+    #[account(mut, address = program_data.owner)]
+    pub protocol_fee_recipient: UncheckedAccount<'info>,
+    /// CHECK: the signer's ban PDA, which must not exist
+    #[account(
+        seeds = [BAN_SEED, event.key().as_ref(), authority.key().as_ref()],
+        bump,
+        constraint = ban.data_is_empty() @ EventError::Banned
+    )]
+    pub ban: UncheckedAccount<'info>,
+    /// CHECK: instructions sysvar, only needed to present an invite
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+    // Only needed when the event charges a token fee; checked in the handler
+    pub fee_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    #[account(mut)]
+    pub payer_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
     #[account(mut)]
+    pub event_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(storage: StorageRef, content_hash: [u8; 32], tag: String)]
+pub struct UploadImageWithTag<'info> {
+    #[account(
+        seeds = [PROGRAM_DATA_SEED],
+        bump = program_data.bump,
+        constraint = !program_data.is_paused(PAUSE_UPLOAD_IMAGE) @ EventError::InstructionPaused
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        mut,
+        seeds = [USER_SEED, user_data.event.as_ref(), user_data.owner.as_ref()],
+        bump = user_data.bump,
+        has_one = event @ EventError::EventMismatch,
+        constraint = user_data.owner == authority.key() @ EventError::UnauthorizedUser,
+        constraint = user_data.is_joined @ EventError::NotJoined,
+        realloc = user_data.to_account_info().data_len() + UploadedImage::space(&storage, &tag),
+        realloc::payer = authority,
+        realloc::zero = false
+    )]
+    pub user_data: Account<'info, UserData>,
+    #[account(
+        seeds = [ROLE_SEED, event.key().as_ref(), authority.key().as_ref()],
+        bump = member_role.bump
+    )]
+    pub member_role: Option<Account<'info, MemberRole>>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveImage<'info> {
+    #[account(
+        seeds = [PROGRAM_DATA_SEED],
+        bump = program_data.bump,
+        has_one = oracle @ EventError::UnauthorizedOracle
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(
+        mut,
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.status != EventStatus::Archived @ EventError::EventArchived
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        init_if_needed,
+        payer = oracle,
+        space = HighlightPage::SPACE,
+        seeds = [HIGHLIGHTS_SEED, event.key().as_ref(), &event.next_highlight_page().to_le_bytes()],
+        bump
+    )]
+    pub highlight_page: Account<'info, HighlightPage>,
+    #[account(
+        mut,
+        seeds = [USER_SEED, user_data.event.as_ref(), user_data.owner.as_ref()],
+        bump = user_data.bump,
+        has_one = event @ EventError::EventMismatch
+    )]
+    pub user_data: Account<'info, UserData>,
+    #[account(mut)]
+    pub oracle: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RejectImage<'info> {
+    #[account(
+        seeds = [PROGRAM_DATA_SEED],
+        bump = program_data.bump,
+        has_one = oracle @ EventError::UnauthorizedOracle
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.status != EventStatus::Archived @ EventError::EventArchived
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        mut,
+        seeds = [USER_SEED, user_data.event.as_ref(), user_data.owner.as_ref()],
+        bump = user_data.bump,
+        has_one = event @ EventError::EventMismatch
+    )]
+    pub user_data: Account<'info, UserData>,
+    pub oracle: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(selfie: String)]
+pub struct SetSelfie<'info> {
+    #[account(
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        mut,
+        seeds = [USER_SEED, user_data.event.as_ref(), user_data.owner.as_ref()],
+        bump = user_data.bump,
+        has_one = event @ EventError::EventMismatch,
+        constraint = user_data.owner == authority.key() @ EventError::UnauthorizedUser,
+        constraint = user_data.is_joined @ EventError::NotJoined,
+        realloc = user_data.to_account_info().data_len() + selfie.len() - user_data.uploader_selfie.len(),
+        realloc::payer = authority,
+        realloc::zero = false
+    )]
+    pub user_data: Account<'info, UserData>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(image_index: u32)]
+pub struct AttestFaceMatch<'info> {
+    #[account(
+        seeds = [PROGRAM_DATA_SEED],
+        bump = program_data.bump,
+        has_one = oracle @ EventError::UnauthorizedOracle
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.status != EventStatus::Archived @ EventError::EventArchived
+    )]
+    pub event: Account<'info, Event>,
+    /// Uploader of the photo
+    #[account(
+        seeds = [USER_SEED, image_user_data.event.as_ref(), image_user_data.owner.as_ref()],
+        bump = image_user_data.bump,
+        has_one = event @ EventError::EventMismatch
+    )]
+    pub image_user_data: Account<'info, UserData>,
+    /// Attendee recognised in the photo
+    #[account(
+        mut,
+        seeds = [USER_SEED, subject_user_data.event.as_ref(), subject_user_data.owner.as_ref()],
+        bump = subject_user_data.bump,
+        has_one = event @ EventError::EventMismatch,
+        realloc = subject_user_data.to_account_info().data_len() + image_user_data.images
+            .get(image_index as usize)
+            .map_or(0, |image| Highlight::space(&image.storage.uri())),
+        realloc::payer = oracle,
+        realloc::zero = false
+    )]
+    pub subject_user_data: Account<'info, UserData>,
+    #[account(mut)]
+    pub oracle: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(match_index: u32)]
+pub struct RemoveFaceMatch<'info> {
+    #[account(
+        seeds = [PROGRAM_DATA_SEED],
+        bump = program_data.bump
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = authority.key() == program_data.oracle
+            || event.role_of(&authority.key(), member_role.as_deref())
+                .is_some_and(Role::can_moderate) @ EventError::UnauthorizedFaceMatchRemoval,
+        constraint = event.status != EventStatus::Archived @ EventError::EventArchived
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        mut,
+        seeds = [USER_SEED, subject_user_data.event.as_ref(), subject_user_data.owner.as_ref()],
+        bump = subject_user_data.bump,
+        has_one = event @ EventError::EventMismatch,
+        realloc = subject_user_data.to_account_info().data_len() - subject_user_data.appears_in
+            .get(match_index as usize)
+            .map_or(0, |photo| Highlight::space(&photo.url)),
+        realloc::payer = oracle,
+        realloc::zero = false
+    )]
+    pub subject_user_data: Account<'info, UserData>,
+    /// CHECK: receives the rent freed by the removal; the oracle paid for the match
+    #[account(mut, address = program_data.oracle)]
+    pub oracle: UncheckedAccount<'info>,
+    #[account(
+        seeds = [ROLE_SEED, event.key().as_ref(), authority.key().as_ref()],
+        bump = member_role.bump
+    )]
+    pub member_role: Option<Account<'info, MemberRole>>,
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(image_index: u32)]
+pub struct DeleteImage<'info> {
+    #[account(
+        mut,
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.status != EventStatus::Archived @ EventError::EventArchived
+    )]
+    pub event: Account<'info, Event>,
+    /// Page holding the deleted image's highlight; only needed for approved images
+    #[account(
+        mut,
+        seeds = [HIGHLIGHTS_SEED, event.key().as_ref(), &highlight_page.page_no.to_le_bytes()],
+        bump = highlight_page.bump
+    )]
+    pub highlight_page: Option<Account<'info, HighlightPage>>,
+    /// Required unless `highlight_page` is itself the tail page
+    #[account(
+        mut,
+        seeds = [HIGHLIGHTS_SEED, event.key().as_ref(), &event.highlight_tail_page().to_le_bytes()],
+        bump = tail_highlight_page.bump
+    )]
+    pub tail_highlight_page: Option<Account<'info, HighlightPage>>,
+    #[account(
+        mut,
+        seeds = [USER_SEED, user_data.event.as_ref(), user_data.owner.as_ref()],
+        bump = user_data.bump,
+        has_one = event @ EventError::EventMismatch,
+        constraint = user_data.owner == authority.key() @ EventError::UnauthorizedUser,
+        constraint = user_data.is_joined @ EventError::NotJoined,
+        realloc = user_data.to_account_info().data_len() - user_data.image_space(image_index as usize),
+        realloc::payer = authority,
+        realloc::zero = false
+    )]
+    pub user_data: Account<'info, UserData>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(highlight_index: u32)]
+pub struct RemoveHighlight<'info> {
+    #[account(
+        mut,
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.role_of(&authority.key(), member_role.as_deref())
+            .is_some_and(Role::can_moderate) @ EventError::UnauthorizedHighlightRemoval,
+        constraint = event.status != EventStatus::Archived @ EventError::EventArchived
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        mut,
+        seeds = [HIGHLIGHTS_SEED, event.key().as_ref(), &highlight_page.page_no.to_le_bytes()],
+        bump = highlight_page.bump
+    )]
+    pub highlight_page: Account<'info, HighlightPage>,
+    /// Required unless `highlight_page` is itself the tail page
+    #[account(
+        mut,
+        seeds = [HIGHLIGHTS_SEED, event.key().as_ref(), &event.highlight_tail_page().to_le_bytes()],
+        bump = tail_highlight_page.bump
+    )]
+    pub tail_highlight_page: Option<Account<'info, HighlightPage>>,
+    #[account(
+        mut,
+        seeds = [USER_SEED, user_data.event.as_ref(), user_data.owner.as_ref()],
+        bump = user_data.bump,
+        has_one = event @ EventError::EventMismatch,
+        realloc = user_data.to_account_info().data_len() - highlight_page.highlights
+            .get(highlight_index as usize)
+            .and_then(|highlight| user_data.approved_image_position(&highlight.content_hash))
+            .map_or(0, |position| user_data.image_space(position)),
+        realloc::payer = uploader,
+        realloc::zero = false
+    )]
+    pub user_data: Account<'info, UserData>,
+    /// CHECK: receives the rent freed by the removal; must be the uploader
+    #[account(mut, address = user_data.owner)]
+    pub uploader: UncheckedAccount<'info>,
+    #[account(
+        seeds = [ROLE_SEED, event.key().as_ref(), authority.key().as_ref()],
+        bump = member_role.bump
+    )]
+    pub member_role: Option<Account<'info, MemberRole>>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(highlight_index: u32, uploader: Pubkey)]
+pub struct RemoveDepartedHighlight<'info> {
+    #[account(
+        mut,
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.role_of(&authority.key(), member_role.as_deref())
+            .is_some_and(Role::can_moderate) @ EventError::UnauthorizedHighlightRemoval,
+        constraint = event.status != EventStatus::Archived @ EventError::EventArchived
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        mut,
+        seeds = [HIGHLIGHTS_SEED, event.key().as_ref(), &highlight_page.page_no.to_le_bytes()],
+        bump = highlight_page.bump
+    )]
+    pub highlight_page: Account<'info, HighlightPage>,
+    /// Required unless `highlight_page` is itself the tail page
+    #[account(
+        mut,
+        seeds = [HIGHLIGHTS_SEED, event.key().as_ref(), &event.highlight_tail_page().to_le_bytes()],
+        bump = tail_highlight_page.bump
+    )]
+    pub tail_highlight_page: Option<Account<'info, HighlightPage>>,
+    /// CHECK: the uploader's `UserData` PDA, which must have been closed
+    #[account(
+        seeds = [USER_SEED, event.key().as_ref(), uploader.as_ref()],
+        bump,
+        constraint = departed_user_data.data_is_empty() @ EventError::UploaderStillJoined
+    )]
+    pub departed_user_data: UncheckedAccount<'info>,
+    #[account(
+        seeds = [ROLE_SEED, event.key().as_ref(), authority.key().as_ref()],
+        bump = member_role.bump
+    )]
+    pub member_role: Option<Account<'info, MemberRole>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct LeaveEvent<'info> {
+    #[account(
+        mut,
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.owner != authority.key() @ EventError::OwnerCannotLeave
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        mut,
+        seeds = [ATTENDEES_SEED, event.key().as_ref(), &attendee_page.page_no.to_le_bytes()],
+        bump = attendee_page.bump
+    )]
+    pub attendee_page: Account<'info, AttendeePage>,
+    /// Required unless `attendee_page` is itself the tail page
+    #[account(
+        mut,
+        seeds = [ATTENDEES_SEED, event.key().as_ref(), &event.attendee_tail_page().to_le_bytes()],
+        bump = tail_attendee_page.bump
+    )]
+    pub tail_attendee_page: Option<Account<'info, AttendeePage>>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [USER_SEED, user_data.event.as_ref(), user_data.owner.as_ref()],
+        bump = user_data.bump,
+        has_one = event @ EventError::EventMismatch,
+        constraint = user_data.owner == authority.key() @ EventError::UnauthorizedUser
+    )]
+    pub user_data: Account<'info, UserData>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveAttendee<'info> {
+    #[account(
+        mut,
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.role_of(&authority.key(), member_role.as_deref())
+            .is_some_and(Role::can_manage_attendees) @ EventError::UnauthorizedAttendeeRemoval,
+        constraint = event.status != EventStatus::Archived @ EventError::EventArchived
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        mut,
+        seeds = [ATTENDEES_SEED, event.key().as_ref(), &attendee_page.page_no.to_le_bytes()],
+        bump = attendee_page.bump
+    )]
+    pub attendee_page: Account<'info, AttendeePage>,
+    /// Required unless `attendee_page` is itself the tail page
+    #[account(
+        mut,
+        seeds = [ATTENDEES_SEED, event.key().as_ref(), &event.attendee_tail_page().to_le_bytes()],
+        bump = tail_attendee_page.bump
+    )]
+    pub tail_attendee_page: Option<Account<'info, AttendeePage>>,
+    #[account(
+        mut,
+        close = attendee,
+        seeds = [USER_SEED, user_data.event.as_ref(), user_data.owner.as_ref()],
+        bump = user_data.bump,
+        has_one = event @ EventError::EventMismatch,
+        constraint = user_data.owner != event.owner @ EventError::CannotRemoveOwner
+    )]
     pub user_data: Account<'info, UserData>,
+    /// CHECK: receives the `UserData` rent; must be the removed attendee
+    #[account(mut, address = user_data.owner)]
+    pub attendee: UncheckedAccount<'info>,
+    #[account(
+        seeds = [ROLE_SEED, event.key().as_ref(), authority.key().as_ref()],
+        bump = member_role.bump
+    )]
+    pub member_role: Option<Account<'info, MemberRole>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(member: Pubkey)]
+pub struct BanMember<'info> {
+    #[account(
+        mut,
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.role_of(&authority.key(), member_role.as_deref())
+            .is_some_and(Role::can_manage_attendees) @ EventError::UnauthorizedAttendeeRemoval
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        init,
+        payer = authority,
+        space = Ban::SPACE,
+        seeds = [BAN_SEED, event.key().as_ref(), member.as_ref()],
+        bump
+    )]
+    pub ban: Account<'info, Ban>,
+    /// CHECK: the member's `UserData` address; holds data only while they are joined
+    #[account(
+        mut,
+        seeds = [USER_SEED, event.key().as_ref(), member.as_ref()],
+        bump
+    )]
+    pub user_data: UncheckedAccount<'info>,
+    /// Page holding the member's attendee entry; required if they are joined
+    #[account(
+        mut,
+        seeds = [ATTENDEES_SEED, event.key().as_ref(), &attendee_page.page_no.to_le_bytes()],
+        bump = attendee_page.bump
+    )]
+    pub attendee_page: Option<Account<'info, AttendeePage>>,
+    /// Required unless `attendee_page` is itself the tail page
+    #[account(
+        mut,
+        seeds = [ATTENDEES_SEED, event.key().as_ref(), &event.attendee_tail_page().to_le_bytes()],
+        bump = tail_attendee_page.bump
+    )]
+    pub tail_attendee_page: Option<Account<'info, AttendeePage>>,
+    /// CHECK: receives the member's `UserData` rent; required if they are joined
+    #[account(mut, address = member)]
+    pub attendee: Option<UncheckedAccount<'info>>,
+    /// CHECK: the member's role PDA; a role would let them lift their own ban
+    #[account(
+        seeds = [ROLE_SEED, event.key().as_ref(), member.as_ref()],
+        bump,
+        constraint = banned_member_role.data_is_empty() @ EventError::MemberHasRole
+    )]
+    pub banned_member_role: UncheckedAccount<'info>,
+    #[account(
+        seeds = [ROLE_SEED, event.key().as_ref(), authority.key().as_ref()],
+        bump = member_role.bump
+    )]
+    pub member_role: Option<Account<'info, MemberRole>>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnbanMember<'info> {
+    #[account(
+        mut,
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.role_of(&authority.key(), member_role.as_deref())
+            .is_some_and(Role::can_manage_attendees) @ EventError::UnauthorizedAttendeeRemoval
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        mut,
+        close = banned_by,
+        seeds = [BAN_SEED, ban.event.as_ref(), ban.member.as_ref()],
+        bump = ban.bump,
+        has_one = event @ EventError::EventMismatch,
+        has_one = banned_by,
+        constraint = ban.member != authority.key() @ EventError::CannotUnbanSelf
+    )]
+    pub ban: Account<'info, Ban>,
+    /// CHECK: gets back the rent it paid for the ban
     #[account(mut)]
+    pub banned_by: UncheckedAccount<'info>,
+    #[account(
+        seeds = [ROLE_SEED, event.key().as_ref(), authority.key().as_ref()],
+        bump = member_role.bump
+    )]
+    pub member_role: Option<Account<'info, MemberRole>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateEvent<'info> {
+    #[account(
+        mut,
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.role_of(&authority.key(), member_role.as_deref())
+            .is_some_and(Role::can_edit_event) @ EventError::UnauthorizedEventUpdate,
+        constraint = event.status != EventStatus::Archived @ EventError::EventArchived
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        seeds = [ROLE_SEED, event.key().as_ref(), authority.key().as_ref()],
+        bump = member_role.bump
+    )]
+    pub member_role: Option<Account<'info, MemberRole>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetEventSchedule<'info> {
+    #[account(
+        mut,
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.role_of(&authority.key(), member_role.as_deref())
+            .is_some_and(Role::can_edit_event) @ EventError::UnauthorizedEventUpdate,
+        constraint = event.status != EventStatus::Archived @ EventError::EventArchived
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        seeds = [VAULT_SEED, event.key().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, EventVault>,
+    #[account(
+        seeds = [ROLE_SEED, event.key().as_ref(), authority.key().as_ref()],
+        bump = member_role.bump
+    )]
+    pub member_role: Option<Account<'info, MemberRole>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(member: Pubkey)]
+pub struct GrantRole<'info> {
+    #[account(
+        mut,
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.owner == authority.key() @ EventError::UnauthorizedRoleChange,
+        constraint = event.status != EventStatus::Archived @ EventError::EventArchived
+    )]
     pub event: Account<'info, Event>,
+    // `init_if_needed` so granting again replaces the member's role
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = MemberRole::SPACE,
+        seeds = [ROLE_SEED, event.key().as_ref(), member.as_ref()],
+        bump
+    )]
+    pub member_role: Account<'info, MemberRole>,
     #[account(mut)]
     pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
 }
 
-#[error_code]
-pub enum EventError {
-    #[msg("Event UID must be 32 characters or less")]
-    UidTooLong,
-    #[msg("Event name must be 50 characters or less")]
-    NameTooLong,
-    #[msg("URL must be 200 characters or less")]
-    UrlTooLong,
-    #[msg("Tag must be 50 characters or less")]
-    TagTooLong,
-    #[msg("User has already joined this event")]
-    AlreadyJoined,
-    #[msg("User must join event before uploading images")]
-    NotJoined,
-    #[msg("Invalid image index")]
-    InvalidImageIndex,
-    #[msg("Only event owner can delete the event")]
-    UnauthorizedDeletion,
-    #[msg("Event count overflow")]
-    EventCountOverflow,
-    #[msg("Event count underflow")]
-    EventCountUnderflow,
-    #[msg("Maximum number of attendees reached")]
-    MaxAttendeesReached,
-    #[msg("Maximum number of images reached")]
-    MaxImagesReached,
+#[derive(Accounts)]
+pub struct RevokeRole<'info> {
+    #[account(
+        mut,
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.owner == authority.key() @ EventError::UnauthorizedRoleChange
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [ROLE_SEED, member_role.event.as_ref(), member_role.member.as_ref()],
+        bump = member_role.bump,
+        has_one = event @ EventError::EventMismatch
+    )]
+    pub member_role: Account<'info, MemberRole>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
 }
 
-#[account]
-#[derive(Default)]
-pub struct ProgramData {
-    pub owner: Pubkey,
-    pub oracle: Pubkey,
-    pub event_count: u64,
+#[derive(Accounts)]
+pub struct SetEventStatus<'info> {
+    #[account(
+        mut,
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.owner == authority.key() @ EventError::UnauthorizedEventUpdate
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        seeds = [VAULT_SEED, event.key().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, EventVault>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_DATA_SEED],
+        bump = program_data.bump
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    pub authority: Signer<'info>,
 }
 
-#[account]
-#[derive(Default)]
-pub struct Event {
-    pub uid: String,
-    pub name: String,
-    pub banner: String,
-    pub owner: Pubkey,
-    pub attendees: Vec<Pubkey>,
-    pub highlight_images: Vec<String>,
+#[derive(Accounts)]
+pub struct CancelEvent<'info> {
+    #[account(
+        mut,
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.owner == authority.key() @ EventError::UnauthorizedEventUpdate
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        seeds = [VAULT_SEED, event.key().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, EventVault>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_DATA_SEED],
+        bump = program_data.bump
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    pub authority: Signer<'info>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
-pub struct UploadedImage {
-    pub url: String,
-    pub tag: String,
-    pub uploader: Pubkey,
+#[derive(Accounts)]
+pub struct DeleteEvent<'info> {
+    #[account(
+        mut,
+        close = authority,
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.owner == authority.key() @ EventError::UnauthorizedDeletion
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [VAULT_SEED, event.key().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, EventVault>,
+    /// CHECK: the owner's `UserData` PDA; closed along with the event if they joined
+    #[account(
+        mut,
+        seeds = [USER_SEED, event.key().as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub owner_user_data: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [PROGRAM_DATA_SEED],
+        bump = program_data.bump
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
 }
 
-#[account]
-#[derive(Default)]
-pub struct UserData {
-    pub uploader_selfie: String,
-    pub is_joined: bool,
-    pub images: Vec<UploadedImage>,
+#[derive(Accounts)]
+pub struct WithdrawProceeds<'info> {
+    #[account(
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.owner == authority.key() @ EventError::UnauthorizedWithdrawal,
+        constraint = event.status != EventStatus::Cancelled @ EventError::EventCancelled
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, event.key().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, EventVault>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct Initialize<'info> {
+pub struct SetTokenFee<'info> {
     #[account(
-        init,
-        payer = owner,
-        space = 8 + std::mem::size_of::<ProgramData>()
+        mut,
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.role_of(&authority.key(), member_role.as_deref())
+            .is_some_and(Role::can_edit_event) @ EventError::UnauthorizedEventUpdate,
+        constraint = event.status != EventStatus::Archived @ EventError::EventArchived
     )]
-    pub program_data: Account<'info, ProgramData>,
+    pub event: Account<'info, Event>,
+    #[account(
+        seeds = [ROLE_SEED, event.key().as_ref(), authority.key().as_ref()],
+        bump = member_role.bump
+    )]
+    pub member_role: Option<Account<'info, MemberRole>>,
+    #[account(
+        seeds = [VAULT_SEED, event.key().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, EventVault>,
+    #[account(mint::token_program = token_program)]
+    pub fee_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = fee_mint,
+        associated_token::authority = event,
+        associated_token::token_program = token_program
+    )]
+    pub event_token_account: InterfaceAccount<'info, TokenAccount>,
     #[account(mut)]
-    pub owner: Signer<'info>,
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct CreateEvent<'info> {
+pub struct WithdrawTokenProceeds<'info> {
     #[account(
-        init,
-        payer = authority,
-        space = 8 + std::mem::size_of::<Event>() + 
-            // Space for dynamic vectors
-            4 + (32 * 10) + // attendees: up to 10 Pubkeys
-            4 + (200 * 10) // highlight_images: up to 50 URLs
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.owner == authority.key() @ EventError::UnauthorizedWithdrawal,
+        constraint = event.status != EventStatus::Cancelled @ EventError::EventCancelled
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, event.key().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, EventVault>,
+    #[account(constraint = event.fee_mint == Some(fee_mint.key()) @ EventError::FeeMintMismatch)]
+    pub fee_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = fee_mint,
+        associated_token::authority = event,
+        associated_token::token_program = token_program
+    )]
+    pub event_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = fee_mint,
+        token::token_program = token_program
+    )]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRefund<'info> {
+    #[account(
+        mut,
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.status == EventStatus::Cancelled @ EventError::EventNotCancelled
     )]
     pub event: Account<'info, Event>,
+    #[account(
+        mut,
+        seeds = [ATTENDEES_SEED, event.key().as_ref(), &attendee_page.page_no.to_le_bytes()],
+        bump = attendee_page.bump
+    )]
+    pub attendee_page: Account<'info, AttendeePage>,
+    /// Required unless `attendee_page` is itself the tail page
+    #[account(
+        mut,
+        seeds = [ATTENDEES_SEED, event.key().as_ref(), &event.attendee_tail_page().to_le_bytes()],
+        bump = tail_attendee_page.bump
+    )]
+    pub tail_attendee_page: Option<Account<'info, AttendeePage>>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [USER_SEED, event.key().as_ref(), authority.key().as_ref()],
+        bump = user_data.bump
+    )]
+    pub user_data: Account<'info, UserData>,
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, event.key().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, EventVault>,
+    // Only needed to refund a token fee; checked in the handler
+    pub fee_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
     #[account(mut)]
-    pub program_data: Account<'info, ProgramData>,
+    pub event_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(mut)]
+    pub destination: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
     #[account(mut)]
     pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct JoinEvent<'info> {
-    #[account(mut)]
+pub struct ClaimBadge<'info> {
+    #[account(
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.status != EventStatus::Cancelled @ EventError::EventCancelled
+    )]
     pub event: Account<'info, Event>,
+    #[account(
+        seeds = [USER_SEED, event.key().as_ref(), authority.key().as_ref()],
+        bump = user_data.bump,
+        constraint = user_data.is_joined @ EventError::NotJoined
+    )]
+    pub user_data: Account<'info, UserData>,
     #[account(
         init,
         payer = authority,
-        space = 8 + std::mem::size_of::<UserData>() +
-            // Space for dynamic vectors
-            4 + (250 * 20) // images: up to 20 UploadedImages
+        seeds = [BADGE_SEED, user_data.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = event,
+        mint::token_program = token_program,
+        extensions::metadata_pointer::authority = event,
+        extensions::metadata_pointer::metadata_address = badge_mint
     )]
-    pub user_data: Account<'info, UserData>,
+    pub badge_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = badge_mint,
+        associated_token::authority = authority,
+        associated_token::token_program = token_program
+    )]
+    pub badge_account: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(mut)]
     pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token2022>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct UploadImageWithTag<'info> {
-    #[account(mut)]
-    pub event: Account<'info, Event>,
+pub struct CreatePhotoCollection<'info> {
     #[account(
         mut,
-        constraint = user_data.is_joined @ EventError::NotJoined
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.owner == authority.key() @ EventError::UnauthorizedEventUpdate,
+        constraint = event.status != EventStatus::Archived @ EventError::EventArchived
     )]
-    pub user_data: Account<'info, UserData>,
+    pub event: Account<'info, Event>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [COLLECTION_SEED, event.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = event,
+        mint::token_program = token_program,
+        extensions::metadata_pointer::authority = event,
+        extensions::metadata_pointer::metadata_address = collection_mint,
+        extensions::group_pointer::authority = event,
+        extensions::group_pointer::group_address = collection_mint
+    )]
+    pub collection_mint: Box<InterfaceAccount<'info, Mint>>,
     #[account(mut)]
     pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct DeleteImage<'info> {
+pub struct MintImage<'info> {
+    #[account(
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump
+    )]
+    pub event: Account<'info, Event>,
     #[account(
         mut,
-        constraint = user_data.is_joined @ EventError::NotJoined
+        seeds = [USER_SEED, event.key().as_ref(), authority.key().as_ref()],
+        bump = user_data.bump
     )]
     pub user_data: Account<'info, UserData>,
+    // Exists once the owner has opted in with `create_photo_collection`
+    #[account(
+        mut,
+        seeds = [COLLECTION_SEED, event.key().as_ref()],
+        bump
+    )]
+    pub collection_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = authority,
+        mint::decimals = 0,
+        mint::authority = event,
+        mint::token_program = token_program,
+        extensions::metadata_pointer::authority = event,
+        extensions::metadata_pointer::metadata_address = image_mint,
+        extensions::group_member_pointer::authority = event,
+        extensions::group_member_pointer::member_address = image_mint
+    )]
+    pub image_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = image_mint,
+        associated_token::authority = authority,
+        associated_token::token_program = token_program
+    )]
+    pub image_account: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(mut)]
     pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token2022>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct DeleteEvent<'info> {
+#[instruction(max_depth: u8, max_buffer_size: u8)]
+pub struct CreateImageTree<'info> {
     #[account(
         mut,
-        close = authority,
-        constraint = event.owner == authority.key() @ EventError::UnauthorizedDeletion
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump,
+        constraint = event.owner == authority.key() @ EventError::UnauthorizedEventUpdate,
+        constraint = event.status != EventStatus::Archived @ EventError::EventArchived
     )]
     pub event: Account<'info, Event>,
+    #[account(
+        init,
+        payer = authority,
+        space = ImageTree::space(max_depth, max_buffer_size),
+        seeds = [IMAGE_TREE_SEED, event.key().as_ref()],
+        bump
+    )]
+    pub image_tree: Account<'info, ImageTree>,
     #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AppendImage<'info> {
+    #[account(
+        seeds = [PROGRAM_DATA_SEED],
+        bump = program_data.bump,
+        constraint = !program_data.is_paused(PAUSE_UPLOAD_IMAGE) @ EventError::InstructionPaused
+    )]
     pub program_data: Account<'info, ProgramData>,
-    #[account(mut)]
+    #[account(
+        seeds = [EVENT_SEED, event.uid.as_bytes()],
+        bump = event.bump
+    )]
+    pub event: Account<'info, Event>,
+    #[account(
+        mut,
+        seeds = [IMAGE_TREE_SEED, event.key().as_ref()],
+        bump = image_tree.bump
+    )]
+    pub image_tree: Account<'info, ImageTree>,
+    #[account(
+        mut,
+        seeds = [USER_SEED, event.key().as_ref(), authority.key().as_ref()],
+        bump = user_data.bump,
+        constraint = user_data.is_joined @ EventError::NotJoined
+    )]
+    pub user_data: Account<'info, UserData>,
+    #[account(
+        seeds = [ROLE_SEED, event.key().as_ref(), authority.key().as_ref()],
+        bump = member_role.bump
+    )]
+    pub member_role: Option<Account<'info, MemberRole>>,
     pub authority: Signer<'info>,
 }
+
+#[derive(Accounts)]
+pub struct VerifyImage<'info> {
+    #[account(
+        seeds = [IMAGE_TREE_SEED, image_tree.event.as_ref()],
+        bump = image_tree.bump
+    )]
+    pub image_tree: Account<'info, ImageTree>,
+}
@@ -1,13 +1,21 @@
-use anchor_client::{anchor_lang, solana_client::rpc_client::RpcClient, solana_sdk::{native_token::LAMPORTS_PER_SOL, signature::Keypair, signer::Signer}};
-
-use crate::test_util::{create_default_event, create_event, initialize_program, join_event, request_airdrop_with_retries, setup, upload_image, EventAccounts, JoinEventAccounts};
+use anchor_client::{anchor_lang, solana_client::rpc_client::RpcClient, solana_sdk::{instruction::{AccountMeta, Instruction}, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signature::Keypair, signer::Signer}};
+use anchor_spl::{
+    token::spl_token,
+    token_2022::spl_token_2022::{self, extension::{
+        group_member_pointer::GroupMemberPointer, group_pointer::GroupPointer, BaseStateWithExtensions,
+        ExtensionType, StateWithExtensions,
+    }},
+    token_2022_extensions::spl_token_metadata_interface::state::TokenMetadata,
+};
+
+use crate::test_util::{admin_keypair, append_image, approve_image, attendee_page_pda, ban_pda, cancel_event, claim_badge, claim_refund, create_default_event, create_event, create_event_with_limits, create_event_with_options, create_fee_mint, create_image_tree, create_photo_collection, event_pda, find_attendee_page, grant_role, image_tree_pda, image_tree_proof, initialize_program, join_event, join_event_with_access, leave_event, member_role_account, member_role_pda, mint_image, oracle_keypair, parse_events, request_airdrop_with_retries, set_event_status, set_paused, set_token_fee, setup, setup_exclusive, token_account, token_balance, upload_image, upload_image_with_storage, user_data_pda, vault_pda, EventAccounts, EventOptions, JoinEventAccounts, DEFAULT_MAX_IMAGES_PER_USER};
 
 #[test]
 fn test_initialize() {
-    let (owner, _, _, program_id, client) = setup();
+    let (_, _, _, program_id, client) = setup();
     
     // Initialize program and get accounts
-    let _program_accounts = initialize_program(&owner, program_id, &client)
+    let _program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
 }
 
@@ -16,7 +24,7 @@ fn test_create_event() {
     let (owner, _, _, program_id, client) = setup();
     
     // Initialize program and get accounts
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
 
     // Now create an event
@@ -28,7 +36,7 @@ fn test_create_event() {
 fn test_create_different_owners() {
     let (owner, alice, bob, program_id, client) = setup();
     
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
     
     // Owner creates an event
@@ -48,7 +56,7 @@ fn test_create_different_owners() {
 fn test_multiple_events() {
     let (owner, _, _, program_id, client) = setup();
     
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
     
     // Create multiple events
@@ -59,7 +67,7 @@ fn test_multiple_events() {
                 &owner,
                 &client,
                 Some((
-                    format!("event_{}", i),
+                    format!("event_{}_{}", i, rand::random::<u32>()),
                     format!("Event {}", i),
                     format!("https://example.com/banner_{}.jpg", i),
                 )),
@@ -78,14 +86,14 @@ fn test_join_event() {
     let (owner, alice, _, program_id, client) = setup();
     
     // Initialize program
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
 
     // Create event
     let owner_event = create_default_event(&program_accounts, &owner, &client)
         .expect("Failed to create owner's event");
     println!("Owner event UID: {}", owner_event.uid);
-    println!("Owner event pubkey: {}", owner_event.event.pubkey());
+    println!("Owner event pubkey: {}", owner_event.event);
 
     // Alice joins the event
     let join_accounts = join_event(&owner_event, &alice, &client)
@@ -99,7 +107,7 @@ fn test_upload_image() {
     let (owner, alice, _, program_id, client) = setup();
     
     // Initialize program
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
 
     // Create event
@@ -127,7 +135,7 @@ fn test_delete_image() {
     let program = client.program(program_id).unwrap();
     
     // Initialize program
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
 
     // Create event
@@ -153,8 +161,12 @@ fn test_delete_image() {
     let tx = program
         .request()
         .accounts(eventsnap::accounts::DeleteImage {
+            event: image_upload.event,
+            highlight_page: None,
+            tail_highlight_page: None,
             user_data: image_upload.user_data,
             authority: alice.pubkey(),
+            system_program: anchor_lang::solana_program::system_program::ID,
         })
         .args(eventsnap::instruction::DeleteImage {
             image_index: 0,
@@ -166,47 +178,418 @@ fn test_delete_image() {
     println!("Delete image transaction signature: {}", tx);
 }
 
+#[test]
+fn test_delete_image_cascades_to_highlights() {
+    let (owner, alice, _, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+
+    let join_accounts = join_event(&event, &alice, &client)
+        .expect("Failed to join event");
+
+    let image_url = "https://example.com/cascade.jpg".to_string();
+    let image_upload = upload_image(
+        &join_accounts,
+        &alice,
+        &client,
+        Some((image_url.clone(), "cascade".to_string()))
+    ).expect("Failed to upload image");
+    let approval = approve_image(&program_accounts, &image_upload, 0, &client)
+        .expect("Failed to approve image");
+
+    let highlight_page: eventsnap::HighlightPage = program.account(approval.highlight_page).unwrap();
+    assert!(highlight_page.highlights.iter().any(|highlight| highlight.url == image_url));
+
+    program
+        .request()
+        .accounts(eventsnap::accounts::DeleteImage {
+            event: image_upload.event,
+            highlight_page: Some(approval.highlight_page),
+            tail_highlight_page: None,
+            user_data: image_upload.user_data,
+            authority: alice.pubkey(),
+            system_program: anchor_lang::solana_program::system_program::ID,
+        })
+        .args(eventsnap::instruction::DeleteImage {
+            image_index: 0,
+        })
+        .signer(&alice)
+        .send()
+        .expect("Failed to delete image");
+
+    let highlight_page: eventsnap::HighlightPage = program.account(approval.highlight_page).unwrap();
+    assert!(highlight_page.highlights.iter().all(|highlight| highlight.url != image_url));
+    let event_account: eventsnap::Event = program.account(event.event).unwrap();
+    assert_eq!(event_account.highlight_count, 0);
+    let user_data: eventsnap::UserData = program.account(image_upload.user_data).unwrap();
+    assert!(user_data.images.is_empty());
+}
+
+#[test]
+fn test_remove_highlight_cascades_to_user_images() {
+    let (owner, alice, _, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+
+    let join_accounts = join_event(&event, &alice, &client)
+        .expect("Failed to join event");
+
+    let image_url = "https://example.com/highlight.jpg".to_string();
+    let image_upload = upload_image(
+        &join_accounts,
+        &alice,
+        &client,
+        Some((image_url.clone(), "highlight".to_string()))
+    ).expect("Failed to upload image");
+    let approval = approve_image(&program_accounts, &image_upload, 0, &client)
+        .expect("Failed to approve image");
+
+    // Alice is not the event owner and cannot remove highlights
+    let result = program
+        .request()
+        .accounts(eventsnap::accounts::RemoveHighlight {
+            event: event.event,
+            highlight_page: approval.highlight_page,
+            tail_highlight_page: None,
+            user_data: image_upload.user_data,
+            uploader: alice.pubkey(),
+            member_role: None,
+            authority: alice.pubkey(),
+            system_program: anchor_lang::solana_program::system_program::ID,
+        })
+        .args(eventsnap::instruction::RemoveHighlight {
+            highlight_index: 0,
+        })
+        .signer(&alice)
+        .send();
+    assert!(format!("{:?}", result.unwrap_err()).contains("UnauthorizedHighlightRemoval"));
+
+    program
+        .request()
+        .accounts(eventsnap::accounts::RemoveHighlight {
+            event: event.event,
+            highlight_page: approval.highlight_page,
+            tail_highlight_page: None,
+            user_data: image_upload.user_data,
+            uploader: alice.pubkey(),
+            member_role: None,
+            authority: owner.pubkey(),
+            system_program: anchor_lang::solana_program::system_program::ID,
+        })
+        .args(eventsnap::instruction::RemoveHighlight {
+            highlight_index: 0,
+        })
+        .signer(&owner)
+        .send()
+        .expect("Failed to remove highlight");
+
+    let highlight_page: eventsnap::HighlightPage = program.account(approval.highlight_page).unwrap();
+    assert!(highlight_page.highlights.is_empty());
+    let user_data: eventsnap::UserData = program.account(image_upload.user_data).unwrap();
+    assert!(user_data.images.iter().all(|image| image.storage.uri() != image_url));
+}
+
 #[test]
 fn test_delete_event() {
-    let (owner, _, _, program_id, client) = setup();
+    let (owner, alice, _, program_id, client) = setup();
     let program = client.program(program_id).unwrap();
 
     // Initialize program
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
 
     // Create event
     let owner_event = create_default_event(&program_accounts, &owner, &client)
         .expect("Failed to create owner's event");
 
+    let attendee_page = attendee_page_pda(&program_id, &owner_event.event, 0);
+
+    let delete = || {
+        program
+            .request()
+            .accounts(eventsnap::accounts::DeleteEvent {
+                event: owner_event.event,
+                vault: vault_pda(&program_id, &owner_event.event),
+                owner_user_data: user_data_pda(&program_id, &owner_event.event, &owner.pubkey()),
+                program_data: program_accounts.program_data,
+                authority: owner.pubkey(),
+            })
+            .accounts(vec![AccountMeta::new(attendee_page, false)])
+            .args(eventsnap::instruction::DeleteEvent {})
+            .signer(&owner)
+            .send()
+    };
+
+    // Roles and bans would carry over to a new event with the same uid
+    let alice_role = member_role_pda(&program_id, &owner_event.event, &alice.pubkey());
+    grant_role(&owner_event, &owner, &alice.pubkey(), eventsnap::Role::CoHost, &client)
+        .expect("Failed to grant co-host role");
+    let result = delete();
+    assert!(format!("{:?}", result.unwrap_err()).contains("EventHasRolesOrBans"));
+    program
+        .request()
+        .accounts(eventsnap::accounts::RevokeRole {
+            event: owner_event.event,
+            member_role: alice_role,
+            authority: owner.pubkey(),
+        })
+        .args(eventsnap::instruction::RevokeRole {})
+        .signer(&owner)
+        .send()
+        .expect("Failed to revoke role");
+
+    let stranger = Keypair::new().pubkey();
+    let ban = ban_pda(&program_id, &owner_event.event, &stranger);
+    program
+        .request()
+        .accounts(eventsnap::accounts::BanMember {
+            event: owner_event.event,
+            ban,
+            user_data: user_data_pda(&program_id, &owner_event.event, &stranger),
+            attendee_page: None,
+            tail_attendee_page: None,
+            attendee: None,
+            banned_member_role: member_role_pda(&program_id, &owner_event.event, &stranger),
+            member_role: None,
+            authority: owner.pubkey(),
+            system_program: anchor_lang::solana_program::system_program::ID,
+        })
+        .args(eventsnap::instruction::BanMember { member: stranger, keep_highlights: false })
+        .signer(&owner)
+        .send()
+        .expect("Failed to ban member");
+    let event_account: eventsnap::Event = program.account(owner_event.event).unwrap();
+    assert_eq!((event_account.role_count, event_account.ban_count), (0, 1));
+    let result = delete();
+    assert!(format!("{:?}", result.unwrap_err()).contains("EventHasRolesOrBans"));
+    program
+        .request()
+        .accounts(eventsnap::accounts::UnbanMember {
+            event: owner_event.event,
+            ban,
+            banned_by: owner.pubkey(),
+            member_role: None,
+            authority: owner.pubkey(),
+        })
+        .args(eventsnap::instruction::UnbanMember {})
+        .signer(&owner)
+        .send()
+        .expect("Failed to unban member");
+
+    let balance = program.rpc().get_balance(&owner.pubkey()).unwrap();
+
     // Owner deletes the event using the correct program ID
-    let tx = program
+    let tx = delete().expect("Failed to delete event");
+
+    println!("Delete event transaction signature: {}", tx);
+
+    // Event and page rent both go back to the owner
+    assert!(program.rpc().get_account(&owner_event.event).is_err());
+    assert!(program.rpc().get_account(&attendee_page).is_err());
+    let rent = program.rpc().get_minimum_balance_for_rent_exemption(eventsnap::Event::SPACE).unwrap()
+        + program.rpc().get_minimum_balance_for_rent_exemption(eventsnap::AttendeePage::SPACE).unwrap();
+    assert!(program.rpc().get_balance(&owner.pubkey()).unwrap() + 10_000 >= balance + rent);
+}
+
+#[test]
+fn test_owner_joins_own_event() {
+    let (owner, _, _, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+
+    // The owner is already an attendee, so joining only sets up their UserData
+    let owner_join = join_event(&event, &owner, &client).expect("Failed to join own event");
+    let event_account: eventsnap::Event = program.account(event.event).unwrap();
+    assert_eq!(event_account.attendee_count, 1);
+    let attendee_page = attendee_page_pda(&program_id, &event.event, 0);
+    let page: eventsnap::AttendeePage = program.account(attendee_page).unwrap();
+    assert_eq!(page.attendees, vec![owner.pubkey()]);
+
+    let image_upload = upload_image(&owner_join, &owner, &client, None)
+        .expect("Failed to upload image");
+    let approval = approve_image(&program_accounts, &image_upload, 0, &client)
+        .expect("Failed to approve image");
+
+    program
         .request()
         .accounts(eventsnap::accounts::DeleteEvent {
-            event: owner_event.event.pubkey(),
-            program_data: program_accounts.program_data.pubkey(),
+            event: event.event,
+            vault: vault_pda(&program_id, &event.event),
+            owner_user_data: owner_join.user_data,
+            program_data: program_accounts.program_data,
             authority: owner.pubkey(),
         })
+        .accounts(vec![
+            AccountMeta::new(attendee_page, false),
+            AccountMeta::new(approval.highlight_page, false),
+        ])
         .args(eventsnap::instruction::DeleteEvent {})
         .signer(&owner)
         .send()
         .expect("Failed to delete event");
+    assert!(program.rpc().get_account(&event.event).is_err());
+    assert!(program.rpc().get_account(&owner_join.user_data).is_err());
+
+    // A recreated event starts without the old owner's UserData
+    create_event(&program_accounts, &owner, &client, Some((
+        event.uid.clone(),
+        "Recreated Event".to_string(),
+        "https://example.com/banner.jpg".to_string(),
+    ))).expect("Failed to recreate event");
+    let result = upload_image(&owner_join, &owner, &client, None);
+    assert!(format!("{:?}", result.err().unwrap()).contains("AccountNotInitialized"));
+}
 
-    println!("Delete event transaction signature: {}", tx);
+#[test]
+fn test_duplicate_event_uid() {
+    let (owner, alice, _, program_id, client) = setup();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+    assert_eq!(event.event, event_pda(&program_id, &event.uid));
+
+    // Alice tries to create an event with the same uid
+    let result = create_event(
+        &program_accounts,
+        &alice,
+        &client,
+        Some((
+            event.uid.clone(),
+            "Another Event".to_string(),
+            "https://example.com/banner.jpg".to_string(),
+        )),
+    );
+
+    assert!(result.is_err());
 }
 
 #[test]
-fn test_event_banner_validation() {} // Test banner URL format
+fn test_event_banner_validation() {
+    let (owner, _, _, program_id, client) = setup();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    let long_banner = format!("https://example.com/{}.jpg", "a".repeat(200));
+    let result = create_event(&program_accounts, &owner, &client, Some((
+        format!("event{}", rand::random::<u32>()),
+        "Banner Event".to_string(),
+        long_banner,
+    )));
+    assert!(format!("{:?}", result.err().unwrap()).contains("BannerTooLong"));
+
+    let max_banner = "b".repeat(200);
+    let event = create_event(&program_accounts, &owner, &client, Some((
+        format!("event{}", rand::random::<u32>()),
+        "Banner Event".to_string(),
+        max_banner.clone(),
+    ))).expect("Failed to create event with a maximum length banner");
+
+    let program = client.program(program_id).unwrap();
+    let event_account: eventsnap::Event = program.account(event.event).unwrap();
+    assert_eq!(event_account.banner, max_banner);
+}
 
 #[test]
 fn test_event_count_underflow() {} // Test min event count
 #[test]
-fn test_oracle_interaction() {} // Test oracle pubkey functionality
+fn test_oracle_interaction() {
+    let (owner, alice, bob, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+    assert_eq!(program_accounts.oracle, oracle_keypair().pubkey());
+
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+
+    let join_accounts = join_event(&event, &alice, &client)
+        .expect("Failed to join event");
+
+    let first_upload = upload_image(
+        &join_accounts,
+        &alice,
+        &client,
+        Some(("https://example.com/approved.jpg".to_string(), "ok".to_string()))
+    ).expect("Failed to upload image");
+    let second_upload = upload_image(
+        &join_accounts,
+        &alice,
+        &client,
+        Some(("https://example.com/rejected.jpg".to_string(), "spam".to_string()))
+    ).expect("Failed to upload image");
+
+    // Uploads stay out of the gallery until moderated
+    let user_data: eventsnap::UserData = program.account(join_accounts.user_data).unwrap();
+    assert!(user_data.images.iter().all(|image| image.status == eventsnap::ImageStatus::Pending));
+    let event_account: eventsnap::Event = program.account(event.event).unwrap();
+    assert_eq!(event_account.highlight_count, 0);
+
+    // Only the oracle may moderate
+    let result = program
+        .request()
+        .accounts(eventsnap::accounts::RejectImage {
+            program_data: program_accounts.program_data,
+            event: event.event,
+            user_data: join_accounts.user_data,
+            oracle: bob.pubkey(),
+        })
+        .args(eventsnap::instruction::RejectImage { image_index: 1 })
+        .signer(&bob)
+        .send();
+    assert!(format!("{:?}", result.unwrap_err()).contains("UnauthorizedOracle"));
+
+    let approval = approve_image(&program_accounts, &first_upload, 0, &client)
+        .expect("Failed to approve image");
+
+    let oracle = oracle_keypair();
+    program
+        .request()
+        .accounts(eventsnap::accounts::RejectImage {
+            program_data: program_accounts.program_data,
+            event: second_upload.event,
+            user_data: second_upload.user_data,
+            oracle: oracle.pubkey(),
+        })
+        .args(eventsnap::instruction::RejectImage { image_index: 1 })
+        .signer(&oracle)
+        .send()
+        .expect("Failed to reject image");
+
+    // An image can only be moderated once
+    let result = approve_image(&program_accounts, &second_upload, 1, &client);
+    assert!(format!("{:?}", result.err().unwrap()).contains("ImageNotPending"));
+
+    let user_data: eventsnap::UserData = program.account(join_accounts.user_data).unwrap();
+    assert!(user_data.images[0].status == eventsnap::ImageStatus::Approved);
+    assert!(user_data.images[1].status == eventsnap::ImageStatus::Rejected);
+    let highlight_page: eventsnap::HighlightPage = program.account(approval.highlight_page).unwrap();
+    assert_eq!(highlight_page.highlights.len(), 1);
+    assert_eq!(highlight_page.highlights[0].url, first_upload.url);
+}
 
 #[test]
 fn test_event_name_too_long() {
     let (owner, _, _, program_id, client) = setup();
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
 
     let long_name = "This event name is definitely longer than fifty characters limit".to_string();
@@ -228,7 +611,7 @@ fn test_event_name_too_long() {
 #[test]
 fn test_event_uid_too_long() {
     let (owner, _, _, program_id, client) = setup();
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
 
     let long_uid = "this_uid_is_definitely_longer_than_thirty_two_characters".to_string();
@@ -252,7 +635,7 @@ fn test_double_join_prevention() {
     let (owner, alice, _, program_id, client) = setup();
     let program = client.program(program_id).unwrap();
     
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
 
     let event = create_default_event(&program_accounts, &owner, &client)
@@ -263,21 +646,32 @@ fn test_double_join_prevention() {
         .expect("Failed to join event");
 
     // Second join - should fail with AlreadyJoined
-    let user_data = Keypair::new();
+    let event_account: eventsnap::Event = program.account(event.event).unwrap();
     let result = program
         .request()
         .accounts(eventsnap::accounts::JoinEvent {
-            event: event.event.pubkey(),
-            user_data: user_data.pubkey(),
+            program_data: program_accounts.program_data,
+            event: event.event,
+            attendee_page: attendee_page_pda(&program_id, &event.event, event_account.next_attendee_page()),
+            user_data: join_accounts.user_data,
+            vault: vault_pda(&program_id, &event.event),
+            protocol_fee_recipient: admin_keypair().pubkey(),
+            member_role: None,
+            ban: ban_pda(&program_id, &event.event, &alice.pubkey()),
+            instructions: None,
+            fee_mint: None,
+            payer_token_account: None,
+            event_token_account: None,
+            token_program: None,
             authority: alice.pubkey(),
             system_program: anchor_lang::solana_program::system_program::ID,
         })
-        .args(eventsnap::instruction::JoinEvent {})
-        .signer(&user_data)
+        .args(eventsnap::instruction::JoinEvent { proof: vec![] })
         .signer(&alice)
         .send();
 
     assert!(result.is_err());
+    assert!(format!("{:?}", result.unwrap_err()).contains("AlreadyJoined"));
 }
 
 #[test]
@@ -285,7 +679,7 @@ fn test_unauthorized_event_deletion() {
     let (owner, alice, _, program_id, client) = setup();
     let program = client.program(program_id).unwrap();
 
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
 
     let event = create_default_event(&program_accounts, &owner, &client)
@@ -295,8 +689,10 @@ fn test_unauthorized_event_deletion() {
     let result = program
         .request()
         .accounts(eventsnap::accounts::DeleteEvent {
-            event: event.event.pubkey(),
-            program_data: program_accounts.program_data.pubkey(),
+            event: event.event,
+            vault: vault_pda(&program_id, &event.event),
+            owner_user_data: user_data_pda(&program_id, &event.event, &alice.pubkey()),
+            program_data: program_accounts.program_data,
             authority: alice.pubkey(),
         })
         .args(eventsnap::instruction::DeleteEvent {})
@@ -310,7 +706,7 @@ fn test_unauthorized_event_deletion() {
 fn test_image_url_too_long() {
     let (owner, alice, _, program_id, client) = setup();
     
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
 
     let event = create_default_event(&program_accounts, &owner, &client)
@@ -335,7 +731,7 @@ fn test_image_url_too_long() {
 fn test_image_tag_too_long() {
     let (owner, alice, _, program_id, client) = setup();
     
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
 
     let event = create_default_event(&program_accounts, &owner, &client)
@@ -361,7 +757,7 @@ fn test_unauthorized_image_deletion() {
     let (owner, alice, bob, program_id, client) = setup();
     let program = client.program(program_id).unwrap();
     
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
 
     let event = create_default_event(&program_accounts, &owner, &client)
@@ -381,8 +777,12 @@ fn test_unauthorized_image_deletion() {
     let result = program
         .request()
         .accounts(eventsnap::accounts::DeleteImage {
+            event: image_upload.event,
+            highlight_page: None,
+            tail_highlight_page: None,
             user_data: image_upload.user_data,
             authority: bob.pubkey(),
+            system_program: anchor_lang::solana_program::system_program::ID,
         })
         .args(eventsnap::instruction::DeleteImage {
             image_index: 0,
@@ -391,6 +791,66 @@ fn test_unauthorized_image_deletion() {
         .send();
 
     assert!(result.is_err());
+    assert!(format!("{:?}", result.unwrap_err()).contains("UnauthorizedUser"));
+}
+
+#[test]
+fn test_upload_into_another_users_slot() {
+    let (owner, alice, bob, program_id, client) = setup();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+
+    let alice_join = join_event(&event, &alice, &client)
+        .expect("Failed to join event");
+    let _bob_join = join_event(&event, &bob, &client)
+        .expect("Failed to join event");
+
+    // Bob attempts to upload into Alice's user data
+    let result = upload_image(
+        &alice_join,
+        &bob,
+        &client,
+        Some(("https://example.com/image.jpg".to_string(), "test".to_string()))
+    );
+
+    assert!(result.is_err());
+    assert!(format!("{:?}", result.err().unwrap()).contains("UnauthorizedUser"));
+}
+
+#[test]
+fn test_user_data_event_mismatch() {
+    let (owner, alice, _, program_id, client) = setup();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    let first_event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+    let second_event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+
+    let join_accounts = join_event(&first_event, &alice, &client)
+        .expect("Failed to join event");
+
+    // Alice uses her first event's user data against the second event
+    let mismatched = JoinEventAccounts {
+        user_data: join_accounts.user_data,
+        event: second_event.event,
+        last_signature: String::new(),
+    };
+    let result = upload_image(
+        &mismatched,
+        &alice,
+        &client,
+        Some(("https://example.com/image.jpg".to_string(), "test".to_string()))
+    );
+
+    assert!(result.is_err());
+    assert!(format!("{:?}", result.err().unwrap()).contains("EventMismatch"));
 }
 
 #[test]
@@ -398,7 +858,7 @@ fn test_unauthorized_image_deletion() {
 fn test_max_images_per_user() {
     let (owner, alice, _, program_id, client) = setup();
     
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
 
     let event = create_default_event(&program_accounts, &owner, &client)
@@ -426,7 +886,7 @@ fn test_max_images_per_user() {
 fn test_max_attendees() {
     let (owner, _, _, program_id, client) = setup();
     
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
 
     let event = create_default_event(&program_accounts, &owner, &client)
@@ -450,7 +910,7 @@ fn test_max_attendees() {
 fn test_event_count_overflow() {
     let (owner, _, _, program_id, client) = setup();
     
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
 
     // Create events until we hit u64::MAX
@@ -468,7 +928,7 @@ fn test_event_count_overflow() {
 fn test_highlight_images_limit() {
     let (owner, alice, _, program_id, client) = setup();
     
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
 
     let event = create_default_event(&program_accounts, &owner, &client)
@@ -501,7 +961,7 @@ fn test_highlight_images_limit() {
 fn test_duplicate_image_upload() {
     let (owner, alice, _, program_id, client) = setup();
     
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
 
     let event = create_default_event(&program_accounts, &owner, &client)
@@ -535,23 +995,27 @@ fn test_upload_without_joining() {
     let (owner, alice, _, program_id, client) = setup();
     let program = client.program(program_id).unwrap();
     
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
 
     let event = create_default_event(&program_accounts, &owner, &client)
         .expect("Failed to create event");
 
     // Try to upload without joining
-    let user_data = Keypair::new();
+    let user_data = user_data_pda(&program_id, &event.event, &alice.pubkey());
     let result = program
         .request()
         .accounts(eventsnap::accounts::UploadImageWithTag {
-            event: event.event.pubkey(),
-            user_data: user_data.pubkey(),
+            program_data: program_accounts.program_data,
+            event: event.event,
+            user_data,
+            member_role: None,
             authority: alice.pubkey(),
+            system_program: anchor_lang::solana_program::system_program::ID,
         })
         .args(eventsnap::instruction::UploadImageWithTag {
-            url: "https://example.com/image.jpg".to_string(),
+            storage: eventsnap::StorageRef::Http("https://example.com/image.jpg".to_string()),
+            content_hash: [1; 32],
             tag: "test".to_string(),
         })
         .signer(&alice)
@@ -565,7 +1029,7 @@ fn test_upload_without_joining() {
 fn test_url_length_validation() {
     let (owner, alice, _, program_id, client) = setup();
     
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
 
     let event = create_default_event(&program_accounts, &owner, &client)
@@ -588,7 +1052,7 @@ fn test_url_length_validation() {
 fn test_tag_length_validation() {
     let (owner, alice, _, program_id, client) = setup();
     
-    let program_accounts = initialize_program(&owner, program_id, &client)
+    let program_accounts = initialize_program(program_id, &client)
         .expect("Failed to initialize program");
 
     let event = create_default_event(&program_accounts, &owner, &client)
@@ -606,3 +1070,2055 @@ fn test_tag_length_validation() {
     ).expect("Should fail with TagTooLong");
 }
 
+#[test]
+fn test_configurable_max_attendees() {
+    let (owner, alice, bob, program_id, client) = setup();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    // The owner counts as the first attendee
+    let event = create_event_with_limits(&program_accounts, &owner, &client, None, 2, 1)
+        .expect("Failed to create event");
+
+    let join_accounts = join_event(&event, &alice, &client)
+        .expect("Failed to join event");
+
+    let result = join_event(&event, &bob, &client);
+    assert!(format!("{:?}", result.err().unwrap()).contains("MaxAttendeesReached"));
+
+    upload_image(&join_accounts, &alice, &client, None)
+        .expect("Failed to upload image");
+    let result = upload_image(&join_accounts, &alice, &client, None);
+    assert!(format!("{:?}", result.err().unwrap()).contains("MaxImagesReached"));
+}
+
+#[test]
+fn test_accounts_grow_and_shrink_with_content() {
+    let (owner, alice, _, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+    let rpc_client = program.rpc();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+
+    let join_accounts = join_event(&event, &alice, &client)
+        .expect("Failed to join event");
+    let user_data_len = rpc_client.get_account_data(&join_accounts.user_data).unwrap().len();
+    let user_data_lamports = rpc_client.get_balance(&join_accounts.user_data).unwrap();
+
+    let image_upload = upload_image(&join_accounts, &alice, &client, None)
+        .expect("Failed to upload image");
+    assert!(rpc_client.get_account_data(&join_accounts.user_data).unwrap().len() > user_data_len);
+    assert!(rpc_client.get_balance(&join_accounts.user_data).unwrap() > user_data_lamports);
+
+    program
+        .request()
+        .accounts(eventsnap::accounts::DeleteImage {
+            event: image_upload.event,
+            highlight_page: None,
+            tail_highlight_page: None,
+            user_data: image_upload.user_data,
+            authority: alice.pubkey(),
+            system_program: anchor_lang::solana_program::system_program::ID,
+        })
+        .args(eventsnap::instruction::DeleteImage {
+            image_index: 0,
+        })
+        .signer(&alice)
+        .send()
+        .expect("Failed to delete image");
+
+    // Deleting the image refunds the rent taken by the upload
+    assert_eq!(rpc_client.get_account_data(&join_accounts.user_data).unwrap().len(), user_data_len);
+    assert_eq!(rpc_client.get_balance(&join_accounts.user_data).unwrap(), user_data_lamports);
+}
+
+#[test]
+fn test_attendees_span_multiple_pages() {
+    let (owner, _, _, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+    let rpc_client = RpcClient::new("http://localhost:8899".to_string());
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    let event = create_event_with_limits(
+        &program_accounts,
+        &owner,
+        &client,
+        None,
+        eventsnap::ATTENDEES_PER_PAGE + 1,
+        1,
+    ).expect("Failed to create event");
+
+    // The owner already fills the first slot of page 0
+    let mut attendees = vec![owner.pubkey()];
+    for _ in 1..=eventsnap::ATTENDEES_PER_PAGE {
+        let new_user = Keypair::new();
+        request_airdrop_with_retries(&rpc_client, &new_user.pubkey(), LAMPORTS_PER_SOL)
+            .expect("Failed to airdrop");
+        join_event(&event, &new_user, &client)
+            .expect("Failed to join event");
+        attendees.push(new_user.pubkey());
+    }
+
+    let event_account: eventsnap::Event = program.account(event.event).unwrap();
+    assert_eq!(event_account.attendee_count, eventsnap::ATTENDEES_PER_PAGE + 1);
+    assert_eq!(event_account.attendee_page_count(), 2);
+
+    // Iterate the pages and check every attendee is listed exactly once
+    let mut listed = vec![];
+    for page_no in 0..event_account.attendee_page_count() {
+        let page: eventsnap::AttendeePage = program
+            .account(attendee_page_pda(&program_id, &event.event, page_no))
+            .unwrap();
+        assert_eq!(page.page_no, page_no);
+        listed.extend(page.attendees);
+    }
+    assert_eq!(listed, attendees);
+}
+
+#[test]
+fn test_delete_image_backfills_from_tail_page() {
+    let (owner, alice, _, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    let image_count = eventsnap::HIGHLIGHTS_PER_PAGE + 1;
+    let event = create_event_with_limits(&program_accounts, &owner, &client, None, 10, image_count)
+        .expect("Failed to create event");
+
+    let join_accounts = join_event(&event, &alice, &client)
+        .expect("Failed to join event");
+
+    let uploads: Vec<_> = (0..image_count)
+        .map(|i| {
+            upload_image(
+                &join_accounts,
+                &alice,
+                &client,
+                Some((format!("https://example.com/paged_{}.jpg", i), "paged".to_string()))
+            ).expect("Failed to upload image")
+        })
+        .collect();
+    let approvals: Vec<_> = uploads
+        .iter()
+        .enumerate()
+        .map(|(i, upload)| {
+            approve_image(&program_accounts, upload, i as u32, &client)
+                .expect("Failed to approve image")
+        })
+        .collect();
+    let first_page = approvals[0].highlight_page;
+    let tail_page = approvals[image_count as usize - 1].highlight_page;
+    assert_ne!(first_page, tail_page);
+
+    // Without the tail page the removal would leave a hole in page 0
+    let result = program
+        .request()
+        .accounts(eventsnap::accounts::DeleteImage {
+            event: event.event,
+            highlight_page: Some(first_page),
+            tail_highlight_page: None,
+            user_data: join_accounts.user_data,
+            authority: alice.pubkey(),
+            system_program: anchor_lang::solana_program::system_program::ID,
+        })
+        .args(eventsnap::instruction::DeleteImage {
+            image_index: 0,
+        })
+        .signer(&alice)
+        .send();
+    assert!(format!("{:?}", result.unwrap_err()).contains("InvalidTailPage"));
+
+    program
+        .request()
+        .accounts(eventsnap::accounts::DeleteImage {
+            event: event.event,
+            highlight_page: Some(first_page),
+            tail_highlight_page: Some(tail_page),
+            user_data: join_accounts.user_data,
+            authority: alice.pubkey(),
+            system_program: anchor_lang::solana_program::system_program::ID,
+        })
+        .args(eventsnap::instruction::DeleteImage {
+            image_index: 0,
+        })
+        .signer(&alice)
+        .send()
+        .expect("Failed to delete image");
+
+    let event_account: eventsnap::Event = program.account(event.event).unwrap();
+    assert_eq!(event_account.highlight_count, eventsnap::HIGHLIGHTS_PER_PAGE);
+    let page: eventsnap::HighlightPage = program.account(first_page).unwrap();
+    assert_eq!(page.highlights.len(), eventsnap::HIGHLIGHTS_PER_PAGE as usize);
+    assert_eq!(page.highlights[0].url, uploads[image_count as usize - 1].url);
+    let tail: eventsnap::HighlightPage = program.account(tail_page).unwrap();
+    assert!(tail.highlights.is_empty());
+}
+
+#[test]
+fn test_face_match_attestation() {
+    let (owner, alice, bob, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+    let oracle = oracle_keypair();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+
+    let alice_join = join_event(&event, &alice, &client)
+        .expect("Failed to join event");
+    let bob_join = join_event(&event, &bob, &client)
+        .expect("Failed to join event");
+
+    let image_upload = upload_image(&alice_join, &alice, &client, None)
+        .expect("Failed to upload image");
+    let approval = approve_image(&program_accounts, &image_upload, 0, &client)
+        .expect("Failed to approve image");
+
+    let attest = |signer: &Keypair| {
+        program
+            .request()
+            .accounts(eventsnap::accounts::AttestFaceMatch {
+                program_data: program_accounts.program_data,
+                event: event.event,
+                image_user_data: alice_join.user_data,
+                subject_user_data: bob_join.user_data,
+                oracle: signer.pubkey(),
+                system_program: anchor_lang::solana_program::system_program::ID,
+            })
+            .args(eventsnap::instruction::AttestFaceMatch { image_index: 0 })
+            .signer(signer)
+            .send()
+    };
+
+    // Bob has no selfie to match against yet
+    let result = attest(&oracle);
+    assert!(format!("{:?}", result.unwrap_err()).contains("SelfieNotSet"));
+
+    program
+        .request()
+        .accounts(eventsnap::accounts::SetSelfie {
+            event: event.event,
+            user_data: bob_join.user_data,
+            authority: bob.pubkey(),
+            system_program: anchor_lang::solana_program::system_program::ID,
+        })
+        .args(eventsnap::instruction::SetSelfie {
+            selfie: "https://example.com/bob_selfie.jpg".to_string(),
+        })
+        .signer(&bob)
+        .send()
+        .expect("Failed to set selfie");
+
+    // Only the oracle may attest
+    let result = attest(&bob);
+    assert!(format!("{:?}", result.unwrap_err()).contains("UnauthorizedOracle"));
+
+    attest(&oracle).expect("Failed to attest face match");
+
+    let result = attest(&oracle);
+    assert!(format!("{:?}", result.unwrap_err()).contains("FaceMatchAlreadyRecorded"));
+
+    let bob_data: eventsnap::UserData = program.account(bob_join.user_data).unwrap();
+    assert_eq!(bob_data.uploader_selfie, "https://example.com/bob_selfie.jpg");
+    assert_eq!(bob_data.appears_in.len(), 1);
+    assert_eq!(bob_data.appears_in[0].url, image_upload.url);
+    assert_eq!(bob_data.appears_in[0].uploader, alice.pubkey());
+
+    // Deleting the photo leaves the match behind until it is removed
+    program
+        .request()
+        .accounts(eventsnap::accounts::DeleteImage {
+            event: event.event,
+            highlight_page: Some(approval.highlight_page),
+            tail_highlight_page: None,
+            user_data: alice_join.user_data,
+            authority: alice.pubkey(),
+            system_program: anchor_lang::solana_program::system_program::ID,
+        })
+        .args(eventsnap::instruction::DeleteImage { image_index: 0 })
+        .signer(&alice)
+        .send()
+        .expect("Failed to delete image");
+
+    let bob_data: eventsnap::UserData = program.account(bob_join.user_data).unwrap();
+    assert_eq!(bob_data.appears_in.len(), 1);
+
+    let remove_match = |signer: &Keypair, match_index| {
+        program
+            .request()
+            .accounts(eventsnap::accounts::RemoveFaceMatch {
+                program_data: program_accounts.program_data,
+                event: event.event,
+                subject_user_data: bob_join.user_data,
+                oracle: oracle.pubkey(),
+                member_role: None,
+                authority: signer.pubkey(),
+                system_program: anchor_lang::solana_program::system_program::ID,
+            })
+            .args(eventsnap::instruction::RemoveFaceMatch { match_index })
+            .signer(signer)
+            .send()
+    };
+
+    let result = remove_match(&bob, 0);
+    assert!(format!("{:?}", result.unwrap_err()).contains("UnauthorizedFaceMatchRemoval"));
+
+    let result = remove_match(&owner, 1);
+    assert!(format!("{:?}", result.unwrap_err()).contains("InvalidImageIndex"));
+
+    remove_match(&owner, 0).expect("Failed to remove face match");
+    let bob_data: eventsnap::UserData = program.account(bob_join.user_data).unwrap();
+    assert!(bob_data.appears_in.is_empty());
+}
+
+#[test]
+fn test_set_oracle() {
+    let (_, alice, _, program_id, client) = setup_exclusive();
+    let program = client.program(program_id).unwrap();
+    let admin = admin_keypair();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    let set_oracle = |signer: &Keypair, oracle| {
+        program
+            .request()
+            .accounts(eventsnap::accounts::UpdateProgramData {
+                program_data: program_accounts.program_data,
+                owner: signer.pubkey(),
+            })
+            .args(eventsnap::instruction::SetOracle { oracle })
+            .signer(signer)
+            .send()
+    };
+
+    let result = set_oracle(&alice, alice.pubkey());
+    assert!(format!("{:?}", result.unwrap_err()).contains("UnauthorizedAdmin"));
+
+    let new_oracle = Keypair::new().pubkey();
+    set_oracle(&admin, new_oracle).expect("Failed to set oracle");
+    let program_data: eventsnap::ProgramData = program.account(program_accounts.program_data).unwrap();
+    assert_eq!(program_data.oracle, new_oracle);
+
+    // Restore the shared oracle for the other tests
+    set_oracle(&admin, oracle_keypair().pubkey()).expect("Failed to restore oracle");
+}
+
+#[test]
+fn test_two_step_ownership_transfer() {
+    let (_, alice, bob, program_id, client) = setup_exclusive();
+    let program = client.program(program_id).unwrap();
+    let admin = admin_keypair();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    let propose_owner = |signer: &Keypair, new_owner| {
+        program
+            .request()
+            .accounts(eventsnap::accounts::UpdateProgramData {
+                program_data: program_accounts.program_data,
+                owner: signer.pubkey(),
+            })
+            .args(eventsnap::instruction::ProposeOwner { new_owner })
+            .signer(signer)
+            .send()
+    };
+    let accept_owner = |signer: &Keypair| {
+        program
+            .request()
+            .accounts(eventsnap::accounts::AcceptOwner {
+                program_data: program_accounts.program_data,
+                new_owner: signer.pubkey(),
+            })
+            .args(eventsnap::instruction::AcceptOwner {})
+            .signer(signer)
+            .send()
+    };
+
+    let result = propose_owner(&alice, alice.pubkey());
+    assert!(format!("{:?}", result.unwrap_err()).contains("UnauthorizedAdmin"));
+
+    propose_owner(&admin, alice.pubkey()).expect("Failed to propose owner");
+    let program_data: eventsnap::ProgramData = program.account(program_accounts.program_data).unwrap();
+    assert_eq!(program_data.owner, admin.pubkey());
+    assert_eq!(program_data.pending_owner, Some(alice.pubkey()));
+
+    let result = accept_owner(&bob);
+    assert!(format!("{:?}", result.unwrap_err()).contains("NotPendingOwner"));
+
+    accept_owner(&alice).expect("Failed to accept ownership");
+    let program_data: eventsnap::ProgramData = program.account(program_accounts.program_data).unwrap();
+    assert_eq!(program_data.owner, alice.pubkey());
+    assert_eq!(program_data.pending_owner, None);
+
+    // Hand ownership back to the shared admin for the other tests
+    propose_owner(&alice, admin.pubkey()).expect("Failed to propose owner");
+    accept_owner(&admin).expect("Failed to accept ownership");
+}
+
+#[test]
+fn test_pause_blocks_writes() {
+    let (owner, alice, _, program_id, client) = setup_exclusive();
+    let program = client.program(program_id).unwrap();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+    let join_accounts = join_event(&event, &alice, &client)
+        .expect("Failed to join event");
+    upload_image(&join_accounts, &alice, &client, None)
+        .expect("Failed to upload image");
+
+    // Only the program owner can pause
+    let result = program
+        .request()
+        .accounts(eventsnap::accounts::UpdateProgramData {
+            program_data: program_accounts.program_data,
+            owner: alice.pubkey(),
+        })
+        .args(eventsnap::instruction::SetPaused { paused: eventsnap::PAUSE_ALL })
+        .signer(&alice)
+        .send();
+    assert!(format!("{:?}", result.unwrap_err()).contains("UnauthorizedAdmin"));
+
+    // Pausing uploads leaves the other classes running
+    set_paused(&program_accounts, eventsnap::PAUSE_UPLOAD_IMAGE, &client)
+        .expect("Failed to pause uploads");
+    let result = upload_image(&join_accounts, &alice, &client, None);
+    assert!(format!("{:?}", result.err().unwrap()).contains("InstructionPaused"));
+    create_default_event(&program_accounts, &owner, &client)
+        .expect("Event creation should not be paused");
+
+    set_paused(&program_accounts, eventsnap::PAUSE_ALL, &client)
+        .expect("Failed to pause everything");
+    let result = create_default_event(&program_accounts, &owner, &client);
+    assert!(format!("{:?}", result.err().unwrap()).contains("InstructionPaused"));
+    let result = join_event(&event, &owner, &client);
+    assert!(format!("{:?}", result.err().unwrap()).contains("InstructionPaused"));
+
+    // Deletions keep working while paused
+    program
+        .request()
+        .accounts(eventsnap::accounts::DeleteImage {
+            event: event.event,
+            highlight_page: None,
+            tail_highlight_page: None,
+            user_data: join_accounts.user_data,
+            authority: alice.pubkey(),
+            system_program: anchor_lang::solana_program::system_program::ID,
+        })
+        .args(eventsnap::instruction::DeleteImage {
+            image_index: 0,
+        })
+        .signer(&alice)
+        .send()
+        .expect("Deletion should not be paused");
+
+    set_paused(&program_accounts, 0, &client).expect("Failed to unpause");
+    upload_image(&join_accounts, &alice, &client, None)
+        .expect("Failed to upload image after unpausing");
+}
+
+#[test]
+fn test_state_changes_emit_events() {
+    let (owner, alice, _, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+    let created: Vec<eventsnap::EventCreated> = parse_events(&client, program_id, &event.last_signature)
+        .expect("Failed to parse logs");
+    assert_eq!(created.len(), 1);
+    assert_eq!(created[0].event, event.event);
+    assert_eq!(created[0].uid, event.uid);
+    assert_eq!(created[0].name, event.name);
+    assert_eq!(created[0].owner, owner.pubkey());
+    assert!(created[0].timestamp > 0);
+
+    let alice_join = join_event(&event, &alice, &client)
+        .expect("Failed to join event");
+    let joined: Vec<eventsnap::AttendeeJoined> = parse_events(&client, program_id, &alice_join.last_signature)
+        .expect("Failed to parse logs");
+    assert_eq!(joined.len(), 1);
+    assert_eq!(joined[0].uid, event.uid);
+    assert_eq!(joined[0].attendee, alice.pubkey());
+    assert_eq!(joined[0].attendee_count, 2);
+
+    let image_upload = upload_image(&alice_join, &alice, &client, Some((
+        "https://example.com/logged.jpg".to_string(),
+        "logged".to_string(),
+    ))).expect("Failed to upload image");
+    let uploaded: Vec<eventsnap::ImageUploaded> = parse_events(&client, program_id, &image_upload.last_signature)
+        .expect("Failed to parse logs");
+    assert_eq!(uploaded.len(), 1);
+    assert_eq!(uploaded[0].uid, event.uid);
+    assert_eq!(uploaded[0].uploader, alice.pubkey());
+    assert_eq!(uploaded[0].url, image_upload.url);
+    assert_eq!(uploaded[0].content_hash, image_upload.content_hash);
+    assert_eq!(uploaded[0].tag, image_upload.tag);
+
+    let approval = approve_image(&program_accounts, &image_upload, 0, &client)
+        .expect("Failed to approve image");
+    let approved: Vec<eventsnap::ImageApproved> = parse_events(&client, program_id, &approval.last_signature)
+        .expect("Failed to parse logs");
+    assert_eq!(approved.len(), 1);
+    assert_eq!(approved[0].url, image_upload.url);
+
+    // Events of one type are not picked up as another
+    let uploaded: Vec<eventsnap::ImageUploaded> = parse_events(&client, program_id, &approval.last_signature)
+        .expect("Failed to parse logs");
+    assert!(uploaded.is_empty());
+
+    let tx = program
+        .request()
+        .accounts(eventsnap::accounts::DeleteImage {
+            event: image_upload.event,
+            highlight_page: Some(approval.highlight_page),
+            tail_highlight_page: None,
+            user_data: image_upload.user_data,
+            authority: alice.pubkey(),
+            system_program: anchor_lang::solana_program::system_program::ID,
+        })
+        .args(eventsnap::instruction::DeleteImage {
+            image_index: 0,
+        })
+        .signer(&alice)
+        .send()
+        .expect("Failed to delete image");
+    let deleted: Vec<eventsnap::ImageDeleted> = parse_events(&client, program_id, &tx.to_string())
+        .expect("Failed to parse logs");
+    assert_eq!(deleted.len(), 1);
+    assert_eq!(deleted[0].uploader, alice.pubkey());
+    assert_eq!(deleted[0].url, image_upload.url);
+    assert_eq!(deleted[0].tag, image_upload.tag);
+
+    leave_event(&alice_join, &alice, &client, false, &[])
+        .expect("Failed to leave event");
+
+    let tx = program
+        .request()
+        .accounts(eventsnap::accounts::DeleteEvent {
+            event: event.event,
+            vault: vault_pda(&program_id, &event.event),
+            owner_user_data: user_data_pda(&program_id, &event.event, &owner.pubkey()),
+            program_data: program_accounts.program_data,
+            authority: owner.pubkey(),
+        })
+        .accounts(vec![AccountMeta::new(attendee_page_pda(&program_id, &event.event, 0), false)])
+        .args(eventsnap::instruction::DeleteEvent {})
+        .signer(&owner)
+        .send()
+        .expect("Failed to delete event");
+    let deleted: Vec<eventsnap::EventDeleted> = parse_events(&client, program_id, &tx.to_string())
+        .expect("Failed to parse logs");
+    assert_eq!(deleted.len(), 1);
+    assert_eq!(deleted[0].uid, event.uid);
+    assert_eq!(deleted[0].owner, owner.pubkey());
+}
+
+#[test]
+fn test_event_time_windows() {
+    let (owner, alice, bob, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+    let now = program.rpc().get_block_time(program.rpc().get_slot().unwrap()).unwrap();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    // The end must come after the start, and uploads cannot close before the end
+    let result = create_event_with_options(&program_accounts, &owner, &client, None, EventOptions {
+        starts_at: now,
+        ends_at: now,
+        uploads_close_at: now,
+        ..Default::default()
+    });
+    assert!(format!("{:?}", result.err().unwrap()).contains("InvalidEventSchedule"));
+
+    let result = create_event_with_options(&program_accounts, &owner, &client, None, EventOptions {
+        starts_at: now,
+        ends_at: now + 3600,
+        uploads_close_at: now + 1800,
+        ..Default::default()
+    });
+    assert!(format!("{:?}", result.err().unwrap()).contains("InvalidEventSchedule"));
+
+    let future_event = create_event_with_options(&program_accounts, &owner, &client, None, EventOptions {
+        starts_at: now + 3600,
+        ends_at: now + 7200,
+        uploads_close_at: now + 7200,
+        ..Default::default()
+    }).expect("Failed to create event");
+    let result = join_event(&future_event, &alice, &client);
+    assert!(format!("{:?}", result.err().unwrap()).contains("EventNotStarted"));
+
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+    let alice_join = join_event(&event, &alice, &client)
+        .expect("Failed to join event");
+
+    let set_schedule = |signer: &Keypair, starts_at, ends_at, uploads_close_at| {
+        program
+            .request()
+            .accounts(eventsnap::accounts::SetEventSchedule {
+                event: event.event,
+                vault: vault_pda(&program_id, &event.event),
+                member_role: None,
+                authority: signer.pubkey(),
+            })
+            .args(eventsnap::instruction::SetEventSchedule {
+                starts_at,
+                ends_at,
+                uploads_close_at,
+            })
+            .signer(signer)
+            .send()
+    };
+
+    let result = set_schedule(&alice, 0, 1, 2);
+    assert!(format!("{:?}", result.unwrap_err()).contains("UnauthorizedEventUpdate"));
+
+    // The event is over but photos are still being collected
+    set_schedule(&owner, now - 7200, now - 3600, now + 3600).expect("Failed to set schedule");
+    let result = join_event(&event, &bob, &client);
+    assert!(format!("{:?}", result.err().unwrap()).contains("EventEnded"));
+    upload_image(&alice_join, &alice, &client, None)
+        .expect("Failed to upload image before uploads close");
+
+    set_schedule(&owner, now - 7200, now - 3600, now - 1800).expect("Failed to set schedule");
+    let result = upload_image(&alice_join, &alice, &client, None);
+    assert!(format!("{:?}", result.err().unwrap()).contains("UploadWindowClosed"));
+
+    let event_account: eventsnap::Event = program.account(event.event).unwrap();
+    assert_eq!(event_account.starts_at, now - 7200);
+    assert_eq!(event_account.ends_at, now - 3600);
+    assert_eq!(event_account.uploads_close_at, now - 1800);
+}
+
+#[test]
+fn test_update_event() {
+    let (owner, alice, bob, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+    join_event(&event, &alice, &client).expect("Failed to join event");
+
+    let update = |signer: &Keypair, args: eventsnap::instruction::UpdateEvent| {
+        program
+            .request()
+            .accounts(eventsnap::accounts::UpdateEvent {
+                event: event.event,
+                member_role: None,
+                authority: signer.pubkey(),
+            })
+            .args(args)
+            .signer(signer)
+            .send()
+    };
+    let no_changes = || eventsnap::instruction::UpdateEvent {
+        name: None,
+        banner: None,
+        max_attendees: None,
+        max_images_per_user: None,
+        entry_fee_lamports: None,
+    };
+
+    let result = update(&bob, eventsnap::instruction::UpdateEvent {
+        name: Some("Hijacked".to_string()),
+        ..no_changes()
+    });
+    assert!(format!("{:?}", result.unwrap_err()).contains("UnauthorizedEventUpdate"));
+
+    let result = update(&owner, eventsnap::instruction::UpdateEvent {
+        name: Some("n".repeat(51)),
+        ..no_changes()
+    });
+    assert!(format!("{:?}", result.unwrap_err()).contains("NameTooLong"));
+
+    let result = update(&owner, eventsnap::instruction::UpdateEvent {
+        banner: Some("b".repeat(201)),
+        ..no_changes()
+    });
+    assert!(format!("{:?}", result.unwrap_err()).contains("BannerTooLong"));
+
+    // Owner and Alice have already joined
+    let result = update(&owner, eventsnap::instruction::UpdateEvent {
+        max_attendees: Some(1),
+        ..no_changes()
+    });
+    assert!(format!("{:?}", result.unwrap_err()).contains("InvalidEventLimit"));
+
+    let result = update(&owner, eventsnap::instruction::UpdateEvent {
+        max_images_per_user: Some(0),
+        ..no_changes()
+    });
+    assert!(format!("{:?}", result.unwrap_err()).contains("InvalidEventLimit"));
+
+    update(&owner, eventsnap::instruction::UpdateEvent {
+        name: Some("Renamed Event".to_string()),
+        max_attendees: Some(2),
+        ..no_changes()
+    }).expect("Failed to update event");
+
+    let event_account: eventsnap::Event = program.account(event.event).unwrap();
+    assert_eq!(event_account.name, "Renamed Event");
+    assert_eq!(event_account.banner, event.banner);
+    assert_eq!(event_account.max_attendees, 2);
+    assert_eq!(event_account.max_images_per_user, DEFAULT_MAX_IMAGES_PER_USER);
+
+    let result = join_event(&event, &bob, &client);
+    assert!(format!("{:?}", result.err().unwrap()).contains("MaxAttendeesReached"));
+}
+
+#[test]
+fn test_event_roles() {
+    let (owner, alice, bob, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+    let system_program = anchor_lang::solana_program::system_program::ID;
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    // Room for the owner and Alice only, one image each
+    let event = create_event_with_limits(&program_accounts, &owner, &client, None, 2, 1)
+        .expect("Failed to create event");
+
+    let result = grant_role(&event, &alice, &alice.pubkey(), eventsnap::Role::CoHost, &client);
+    assert!(format!("{:?}", result.err().unwrap()).contains("UnauthorizedRoleChange"));
+    let result = grant_role(&event, &owner, &alice.pubkey(), eventsnap::Role::Owner, &client);
+    assert!(format!("{:?}", result.err().unwrap()).contains("InvalidRole"));
+
+    // Photographers are not bound by the attendee or image caps
+    grant_role(&event, &owner, &bob.pubkey(), eventsnap::Role::Photographer, &client)
+        .expect("Failed to grant photographer role");
+    let alice_join = join_event(&event, &alice, &client)
+        .expect("Failed to join event");
+    let bob_join = join_event(&event, &bob, &client)
+        .expect("Photographer should join beyond the attendee cap");
+    for i in 0..2 {
+        upload_image(&bob_join, &bob, &client, Some((
+            format!("https://example.com/shot{}.jpg", i),
+            "photographer".to_string(),
+        ))).expect("Photographer should upload beyond the image cap");
+    }
+    let alice_upload = upload_image(&alice_join, &alice, &client, None)
+        .expect("Failed to upload image");
+    let result = upload_image(&alice_join, &alice, &client, None);
+    assert!(format!("{:?}", result.err().unwrap()).contains("MaxImagesReached"));
+
+    // Co-hosts can edit the event but not delete it
+    let alice_role = member_role_pda(&program_id, &event.event, &alice.pubkey());
+    grant_role(&event, &owner, &alice.pubkey(), eventsnap::Role::CoHost, &client)
+        .expect("Failed to grant co-host role");
+    let update = |member_role| {
+        program
+            .request()
+            .accounts(eventsnap::accounts::UpdateEvent {
+                event: event.event,
+                member_role,
+                authority: alice.pubkey(),
+            })
+            .args(eventsnap::instruction::UpdateEvent {
+                name: Some("Co-hosted Event".to_string()),
+                banner: None,
+                max_attendees: None,
+                max_images_per_user: None,
+                entry_fee_lamports: None,
+            })
+            .signer(&alice)
+            .send()
+    };
+    update(Some(alice_role)).expect("Co-host should edit the event");
+    let event_account: eventsnap::Event = program.account(event.event).unwrap();
+    assert_eq!(event_account.name, "Co-hosted Event");
+
+    let result = program
+        .request()
+        .accounts(eventsnap::accounts::DeleteEvent {
+            event: event.event,
+            vault: vault_pda(&program_id, &event.event),
+            owner_user_data: user_data_pda(&program_id, &event.event, &alice.pubkey()),
+            program_data: program_accounts.program_data,
+            authority: alice.pubkey(),
+        })
+        .args(eventsnap::instruction::DeleteEvent {})
+        .signer(&alice)
+        .send();
+    assert!(format!("{:?}", result.unwrap_err()).contains("UnauthorizedDeletion"));
+
+    // Moderators can remove any image, co-hosts cannot
+    let approval = approve_image(&program_accounts, &alice_upload, 0, &client)
+        .expect("Failed to approve image");
+    grant_role(&event, &owner, &bob.pubkey(), eventsnap::Role::Moderator, &client)
+        .expect("Failed to replace role with moderator");
+    let bob_role = member_role_pda(&program_id, &event.event, &bob.pubkey());
+    let remove = |signer: &Keypair, member_role| {
+        program
+            .request()
+            .accounts(eventsnap::accounts::RemoveHighlight {
+                event: event.event,
+                highlight_page: approval.highlight_page,
+                tail_highlight_page: None,
+                user_data: alice_upload.user_data,
+                uploader: alice.pubkey(),
+                member_role,
+                authority: signer.pubkey(),
+                system_program,
+            })
+            .args(eventsnap::instruction::RemoveHighlight {
+                highlight_index: 0,
+            })
+            .signer(signer)
+            .send()
+    };
+    let result = remove(&alice, Some(alice_role));
+    assert!(format!("{:?}", result.unwrap_err()).contains("UnauthorizedHighlightRemoval"));
+    remove(&bob, Some(bob_role)).expect("Moderator should remove any image");
+    let highlight_page: eventsnap::HighlightPage = program.account(approval.highlight_page).unwrap();
+    assert!(highlight_page.highlights.is_empty());
+
+    // Revoking closes the role account and takes the permissions with it
+    program
+        .request()
+        .accounts(eventsnap::accounts::RevokeRole {
+            event: event.event,
+            member_role: alice_role,
+            authority: owner.pubkey(),
+        })
+        .args(eventsnap::instruction::RevokeRole {})
+        .signer(&owner)
+        .send()
+        .expect("Failed to revoke role");
+    assert!(program.rpc().get_account(&alice_role).is_err());
+    let result = update(None);
+    assert!(format!("{:?}", result.unwrap_err()).contains("UnauthorizedEventUpdate"));
+}
+
+#[test]
+fn test_private_event_access() {
+    let (owner, alice, bob, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    let set_access_mode = |event: &EventAccounts, access_mode, allowlist_root| {
+        program
+            .request()
+            .accounts(eventsnap::accounts::UpdateEvent {
+                event: event.event,
+                member_role: None,
+                authority: owner.pubkey(),
+            })
+            .args(eventsnap::instruction::SetAccessMode {
+                access_mode,
+                allowlist_root,
+            })
+            .signer(&owner)
+            .send()
+            .expect("Failed to set access mode");
+    };
+
+    // Invite-only events need a voucher signed by the owner
+    let wedding = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+    set_access_mode(&wedding, eventsnap::AccessMode::InviteOnly, [0; 32]);
+
+    let result = join_event(&wedding, &alice, &client);
+    assert!(format!("{:?}", result.err().unwrap()).contains("InviteRequired"));
+    let result = join_event_with_access(&wedding, &alice, &client, vec![], Some(&bob));
+    assert!(format!("{:?}", result.err().unwrap()).contains("InvalidInvite"));
+    join_event_with_access(&wedding, &alice, &client, vec![], Some(&owner))
+        .expect("Failed to join with an owner invite");
+
+    // Allowlist events check a merkle proof against the stored root
+    let others = [Keypair::new().pubkey(), Keypair::new().pubkey()];
+    let leaves = [
+        eventsnap::allowlist_leaf(&alice.pubkey()),
+        eventsnap::allowlist_leaf(&others[0]),
+        eventsnap::allowlist_leaf(&others[1]),
+    ];
+    let root = eventsnap::allowlist_node(&eventsnap::allowlist_node(&leaves[0], &leaves[1]), &leaves[2]);
+    let alice_proof = vec![leaves[1], leaves[2]];
+
+    let album = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+    set_access_mode(&album, eventsnap::AccessMode::Allowlist, root);
+
+    let result = join_event(&album, &alice, &client);
+    assert!(format!("{:?}", result.err().unwrap()).contains("NotOnAllowlist"));
+    let result = join_event_with_access(&album, &bob, &client, alice_proof.clone(), None);
+    assert!(format!("{:?}", result.err().unwrap()).contains("NotOnAllowlist"));
+    join_event_with_access(&album, &alice, &client, alice_proof, None)
+        .expect("Failed to join with an allowlist proof");
+
+    let event_account: eventsnap::Event = program.account(album.event).unwrap();
+    assert!(event_account.access_mode == eventsnap::AccessMode::Allowlist);
+    assert_eq!(event_account.allowlist_root, root);
+    assert_eq!(event_account.attendee_count, 2);
+}
+
+#[test]
+fn test_leave_event() {
+    let (owner, alice, bob, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+    let alice_join = join_event(&event, &alice, &client)
+        .expect("Failed to join event");
+    let bob_join = join_event(&event, &bob, &client)
+        .expect("Failed to join event");
+
+    let mut highlight_page = None;
+    for (join, signer, name) in [(&alice_join, &alice, "alice1"), (&alice_join, &alice, "alice2"), (&bob_join, &bob, "bob")] {
+        let upload = upload_image(join, signer, &client, Some((
+            format!("https://example.com/{}.jpg", name),
+            "leave".to_string(),
+        ))).expect("Failed to upload image");
+        let user_data: eventsnap::UserData = program.account(join.user_data).unwrap();
+        let approval = approve_image(&program_accounts, &upload, user_data.images.len() as u32 - 1, &client)
+            .expect("Failed to approve image");
+        highlight_page = Some(approval.highlight_page);
+    }
+    let highlight_page = highlight_page.unwrap();
+
+    // Alice's highlights can only be dropped if their pages are supplied
+    let result = leave_event(&alice_join, &alice, &client, false, &[]);
+    assert!(format!("{:?}", result.err().unwrap()).contains("HighlightNotFound"));
+
+    let alice_balance = program.rpc().get_balance(&alice.pubkey()).unwrap();
+    leave_event(&alice_join, &alice, &client, false, &[highlight_page])
+        .expect("Failed to leave event");
+    assert!(program.rpc().get_account(&alice_join.user_data).is_err());
+    assert!(program.rpc().get_balance(&alice.pubkey()).unwrap() > alice_balance);
+
+    let event_account: eventsnap::Event = program.account(event.event).unwrap();
+    assert_eq!(event_account.attendee_count, 2);
+    assert_eq!(event_account.highlight_count, 1);
+    let attendee_page: eventsnap::AttendeePage = program.account(
+        attendee_page_pda(&program_id, &event.event, 0),
+    ).unwrap();
+    assert!(!attendee_page.attendees.contains(&alice.pubkey()));
+    let page: eventsnap::HighlightPage = program.account(highlight_page).unwrap();
+    assert_eq!(page.highlights.len(), 1);
+    assert_eq!(page.highlights[0].uploader, bob.pubkey());
+
+    // Bob keeps his photo in the gallery after leaving
+    let remove_departed = || {
+        program
+            .request()
+            .accounts(eventsnap::accounts::RemoveDepartedHighlight {
+                event: event.event,
+                highlight_page,
+                tail_highlight_page: None,
+                departed_user_data: bob_join.user_data,
+                member_role: None,
+                authority: owner.pubkey(),
+            })
+            .args(eventsnap::instruction::RemoveDepartedHighlight {
+                highlight_index: 0,
+                uploader: bob.pubkey(),
+            })
+            .signer(&owner)
+            .send()
+    };
+    let result = remove_departed();
+    assert!(format!("{:?}", result.unwrap_err()).contains("UploaderStillJoined"));
+
+    leave_event(&bob_join, &bob, &client, true, &[])
+        .expect("Failed to leave event");
+    let page: eventsnap::HighlightPage = program.account(highlight_page).unwrap();
+    assert_eq!(page.highlights.len(), 1);
+
+    // Once he is gone a moderator can still take it down
+    remove_departed().expect("Failed to remove departed highlight");
+    let page: eventsnap::HighlightPage = program.account(highlight_page).unwrap();
+    assert!(page.highlights.is_empty());
+
+    // Leaving is not permanent
+    join_event(&event, &alice, &client).expect("Failed to rejoin event");
+}
+
+#[test]
+fn test_remove_and_ban_attendee() {
+    let (owner, alice, bob, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+    let alice_join = join_event(&event, &alice, &client)
+        .expect("Failed to join event");
+    let bob_join = join_event(&event, &bob, &client).expect("Failed to join event");
+
+    let image_upload = upload_image(&alice_join, &alice, &client, None)
+        .expect("Failed to upload image");
+    let approval = approve_image(&program_accounts, &image_upload, 0, &client)
+        .expect("Failed to approve image");
+
+    // Pass `joined` to also hand over the accounts needed to remove the member
+    let ban_ix = |signer: &Keypair, member: Pubkey, joined: bool| {
+        let (attendee_page, tail_attendee_page) = if joined {
+            let (page, tail) = find_attendee_page(&program, &event.event, &member).unwrap();
+            (Some(page), tail)
+        } else {
+            (None, None)
+        };
+        program
+            .request()
+            .accounts(eventsnap::accounts::BanMember {
+                event: event.event,
+                ban: ban_pda(&program_id, &event.event, &member),
+                user_data: user_data_pda(&program_id, &event.event, &member),
+                attendee_page,
+                tail_attendee_page,
+                attendee: joined.then_some(member),
+                banned_member_role: member_role_pda(&program_id, &event.event, &member),
+                member_role: None,
+                authority: signer.pubkey(),
+                system_program: anchor_lang::solana_program::system_program::ID,
+            })
+            .accounts(vec![AccountMeta::new(approval.highlight_page, false)])
+            .args(eventsnap::instruction::BanMember { member, keep_highlights: false })
+            .instructions()
+            .unwrap()
+    };
+    let remove_ix = |signer: &Keypair, join: &JoinEventAccounts, member: Pubkey, keep_highlights| {
+        let (attendee_page, tail_attendee_page) = find_attendee_page(&program, &event.event, &member).unwrap();
+        program
+            .request()
+            .accounts(eventsnap::accounts::RemoveAttendee {
+                event: event.event,
+                attendee_page,
+                tail_attendee_page,
+                user_data: join.user_data,
+                attendee: member,
+                member_role: None,
+                authority: signer.pubkey(),
+            })
+            .accounts(vec![AccountMeta::new(approval.highlight_page, false)])
+            .args(eventsnap::instruction::RemoveAttendee { keep_highlights })
+            .instructions()
+            .unwrap()
+    };
+    let send = |signer: &Keypair, instructions: Vec<Vec<Instruction>>| {
+        let mut request = program.request();
+        for ix in instructions.into_iter().flatten() {
+            request = request.instruction(ix);
+        }
+        request.signer(signer).send()
+    };
+
+    // Regular attendees cannot kick others
+    let result = send(&bob, vec![remove_ix(&bob, &alice_join, alice.pubkey(), false)]);
+    assert!(format!("{:?}", result.unwrap_err()).contains("UnauthorizedAttendeeRemoval"));
+
+    let result = send(&owner, vec![ban_ix(&owner, owner.pubkey(), false)]);
+    assert!(format!("{:?}", result.unwrap_err()).contains("CannotRemoveOwner"));
+
+    // A joined member cannot be banned while left in place
+    let result = send(&owner, vec![ban_ix(&owner, alice.pubkey(), false)]);
+    assert!(format!("{:?}", result.unwrap_err()).contains("AttendeeAccountsRequired"));
+
+    // Banning a joined member kicks them and purges their photos
+    let tx = send(&owner, vec![ban_ix(&owner, alice.pubkey(), true)])
+        .expect("Failed to ban attendee");
+
+    assert!(program.rpc().get_account(&alice_join.user_data).is_err());
+    let highlight_page: eventsnap::HighlightPage = program.account(approval.highlight_page).unwrap();
+    assert!(highlight_page.highlights.is_empty());
+    let event_account: eventsnap::Event = program.account(event.event).unwrap();
+    assert_eq!(event_account.attendee_count, 2);
+    assert_eq!(event_account.highlight_count, 0);
+
+    let banned: Vec<eventsnap::MemberBanned> = parse_events(&client, program_id, &tx.to_string())
+        .expect("Failed to parse logs");
+    assert_eq!(banned.len(), 1);
+    assert_eq!(banned[0].member, alice.pubkey());
+    let removed: Vec<eventsnap::AttendeeRemoved> = parse_events(&client, program_id, &tx.to_string())
+        .expect("Failed to parse logs");
+    assert_eq!(removed.len(), 1);
+    assert_eq!(removed[0].attendee, alice.pubkey());
+    assert_eq!(removed[0].highlights_removed, 1);
+
+    let result = upload_image(&alice_join, &alice, &client, None);
+    assert!(result.is_err());
+    let result = join_event(&event, &alice, &client);
+    assert!(format!("{:?}", result.err().unwrap()).contains("Banned"));
+
+    let unban = |signer: &Keypair, member: Pubkey| {
+        program
+            .request()
+            .accounts(eventsnap::accounts::UnbanMember {
+                event: event.event,
+                ban: ban_pda(&program_id, &event.event, &member),
+                banned_by: owner.pubkey(),
+                member_role: member_role_account(&program, &event.event, &signer.pubkey()),
+                authority: signer.pubkey(),
+            })
+            .args(eventsnap::instruction::UnbanMember {})
+            .signer(signer)
+            .send()
+    };
+    let revoke = |member: Pubkey| {
+        program
+            .request()
+            .accounts(eventsnap::accounts::RevokeRole {
+                event: event.event,
+                member_role: member_role_pda(&program_id, &event.event, &member),
+                authority: owner.pubkey(),
+            })
+            .args(eventsnap::instruction::RevokeRole {})
+            .signer(&owner)
+            .send()
+    };
+
+    // Members who never joined are simply banned
+    let stranger = Keypair::new();
+    send(&owner, vec![ban_ix(&owner, stranger.pubkey(), false)]).expect("Failed to ban non-member");
+
+    // A role handed out later still cannot lift its holder's own ban
+    grant_role(&event, &owner, &stranger.pubkey(), eventsnap::Role::CoHost, &client)
+        .expect("Failed to grant co-host role");
+    let result = unban(&stranger, stranger.pubkey());
+    assert!(format!("{:?}", result.unwrap_err()).contains("CannotUnbanSelf"));
+    revoke(stranger.pubkey()).expect("Failed to revoke role");
+
+    // Staff must lose their role before they can be banned
+    grant_role(&event, &owner, &bob.pubkey(), eventsnap::Role::CoHost, &client)
+        .expect("Failed to grant co-host role");
+    let result = send(&owner, vec![ban_ix(&owner, bob.pubkey(), true)]);
+    assert!(format!("{:?}", result.unwrap_err()).contains("MemberHasRole"));
+    revoke(bob.pubkey()).expect("Failed to revoke role");
+
+    // Photos can stay behind when the gallery is too big to purge in one go
+    let bob_upload = upload_image(&bob_join, &bob, &client, None)
+        .expect("Failed to upload image");
+    approve_image(&program_accounts, &bob_upload, 0, &client)
+        .expect("Failed to approve image");
+    send(&owner, vec![remove_ix(&owner, &bob_join, bob.pubkey(), true)]).expect("Failed to remove attendee");
+    assert!(program.rpc().get_account(&bob_join.user_data).is_err());
+    let highlight_page: eventsnap::HighlightPage = program.account(approval.highlight_page).unwrap();
+    assert_eq!(highlight_page.highlights.len(), 1);
+    assert_eq!(highlight_page.highlights[0].uploader, bob.pubkey());
+
+    program
+        .request()
+        .accounts(eventsnap::accounts::RemoveDepartedHighlight {
+            event: event.event,
+            highlight_page: approval.highlight_page,
+            tail_highlight_page: None,
+            departed_user_data: bob_join.user_data,
+            member_role: None,
+            authority: owner.pubkey(),
+        })
+        .args(eventsnap::instruction::RemoveDepartedHighlight {
+            highlight_index: 0,
+            uploader: bob.pubkey(),
+        })
+        .signer(&owner)
+        .send()
+        .expect("Failed to remove departed highlight");
+    let event_account: eventsnap::Event = program.account(event.event).unwrap();
+    assert_eq!(event_account.highlight_count, 0);
+
+    // Kicking without a ban leaves the door open
+    join_event(&event, &bob, &client).expect("Failed to rejoin after removal");
+
+    unban(&owner, alice.pubkey()).expect("Failed to unban member");
+    join_event(&event, &alice, &client).expect("Failed to rejoin after unban");
+}
+
+#[test]
+fn test_close_then_delete_event() {
+    let (owner, alice, bob, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+    let alice_join = join_event(&event, &alice, &client)
+        .expect("Failed to join event");
+    let image_upload = upload_image(&alice_join, &alice, &client, None)
+        .expect("Failed to upload image");
+    let approval = approve_image(&program_accounts, &image_upload, 0, &client)
+        .expect("Failed to approve image");
+    let attendee_page = attendee_page_pda(&program_id, &event.event, 0);
+
+    let delete = |pages: &[Pubkey]| {
+        program
+            .request()
+            .accounts(eventsnap::accounts::DeleteEvent {
+                event: event.event,
+                vault: vault_pda(&program_id, &event.event),
+                owner_user_data: user_data_pda(&program_id, &event.event, &owner.pubkey()),
+                program_data: program_accounts.program_data,
+                authority: owner.pubkey(),
+            })
+            .accounts(pages.iter().map(|page| AccountMeta::new(*page, false)).collect::<Vec<_>>())
+            .args(eventsnap::instruction::DeleteEvent {})
+            .signer(&owner)
+            .send()
+    };
+    let pages = [attendee_page, approval.highlight_page];
+
+    // Alice's UserData would be stranded
+    let result = delete(&pages);
+    assert!(format!("{:?}", result.unwrap_err()).contains("EventHasAttendees"));
+
+    set_event_status(&program_accounts, &event, &owner, eventsnap::EventStatus::Closed, &client)
+        .expect("Failed to close event");
+
+    // Nothing new comes in once closed
+    let result = join_event(&event, &bob, &client);
+    assert!(format!("{:?}", result.err().unwrap()).contains("EventNotActive"));
+    let result = upload_image(&alice_join, &alice, &client, None);
+    assert!(format!("{:?}", result.err().unwrap()).contains("EventNotActive"));
+
+    // Alice reclaims her rent, then the owner can delete
+    let alice_balance = program.rpc().get_balance(&alice.pubkey()).unwrap();
+    leave_event(&alice_join, &alice, &client, true, &[])
+        .expect("Failed to leave closed event");
+    assert!(program.rpc().get_balance(&alice.pubkey()).unwrap() > alice_balance);
+
+    // Every page has to go, or a new event with this uid would inherit it
+    let result = delete(&pages[..1]);
+    assert!(format!("{:?}", result.unwrap_err()).contains("EventPagesRequired"));
+    let result = delete(&[approval.highlight_page, attendee_page]);
+    assert!(format!("{:?}", result.unwrap_err()).contains("EventPagesRequired"));
+
+    delete(&pages).expect("Failed to delete event");
+    assert!(program.rpc().get_account(&event.event).is_err());
+    assert!(program.rpc().get_account(&attendee_page).is_err());
+    assert!(program.rpc().get_account(&approval.highlight_page).is_err());
+}
+
+#[test]
+fn test_event_status_transitions() {
+    let (owner, alice, bob, program_id, client) = setup_exclusive();
+    let program = client.program(program_id).unwrap();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+    let counts = || {
+        let program_data: eventsnap::ProgramData = program.account(program_accounts.program_data).unwrap();
+        (program_data.event_count, program_data.active_event_count)
+    };
+    let (total, active) = counts();
+
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+    let event_account: eventsnap::Event = program.account(event.event).unwrap();
+    assert!(event_account.status == eventsnap::EventStatus::Active);
+    assert_eq!(counts(), (total + 1, active + 1));
+    let alice_join = join_event(&event, &alice, &client)
+        .expect("Failed to join event");
+
+    let result = set_event_status(&program_accounts, &event, &alice, eventsnap::EventStatus::Closed, &client);
+    assert!(format!("{:?}", result.err().unwrap()).contains("UnauthorizedEventUpdate"));
+    let result = set_event_status(&program_accounts, &event, &owner, eventsnap::EventStatus::Draft, &client);
+    assert!(format!("{:?}", result.err().unwrap()).contains("InvalidStatusTransition"));
+
+    // Closing and reopening moves the event in and out of the active count
+    set_event_status(&program_accounts, &event, &owner, eventsnap::EventStatus::Closed, &client)
+        .expect("Failed to close event");
+    assert_eq!(counts(), (total + 1, active));
+    set_event_status(&program_accounts, &event, &owner, eventsnap::EventStatus::Active, &client)
+        .expect("Failed to reopen event");
+    assert_eq!(counts(), (total + 1, active + 1));
+    let image_upload = upload_image(&alice_join, &alice, &client, None)
+        .expect("Failed to upload to reopened event");
+    let approval = approve_image(&program_accounts, &image_upload, 0, &client)
+        .expect("Failed to approve image");
+
+    set_event_status(&program_accounts, &event, &owner, eventsnap::EventStatus::Archived, &client)
+        .expect("Failed to archive event");
+    assert_eq!(counts(), (total + 1, active));
+
+    // Archived albums take nothing new and cannot be deleted or revived
+    let result = join_event(&event, &bob, &client);
+    assert!(format!("{:?}", result.err().unwrap()).contains("EventNotActive"));
+    let result = upload_image(&alice_join, &alice, &client, None);
+    assert!(format!("{:?}", result.err().unwrap()).contains("EventNotActive"));
+    let result = set_event_status(&program_accounts, &event, &owner, eventsnap::EventStatus::Active, &client);
+    assert!(format!("{:?}", result.err().unwrap()).contains("EventArchived"));
+
+    // Nor can its photos or settings change
+    let result = program
+        .request()
+        .accounts(eventsnap::accounts::DeleteImage {
+            event: event.event,
+            highlight_page: Some(approval.highlight_page),
+            tail_highlight_page: None,
+            user_data: alice_join.user_data,
+            authority: alice.pubkey(),
+            system_program: anchor_lang::solana_program::system_program::ID,
+        })
+        .args(eventsnap::instruction::DeleteImage { image_index: 0 })
+        .signer(&alice)
+        .send();
+    assert!(format!("{:?}", result.unwrap_err()).contains("EventArchived"));
+    let result = program
+        .request()
+        .accounts(eventsnap::accounts::RemoveHighlight {
+            event: event.event,
+            highlight_page: approval.highlight_page,
+            tail_highlight_page: None,
+            user_data: alice_join.user_data,
+            uploader: alice.pubkey(),
+            member_role: None,
+            authority: owner.pubkey(),
+            system_program: anchor_lang::solana_program::system_program::ID,
+        })
+        .args(eventsnap::instruction::RemoveHighlight { highlight_index: 0 })
+        .signer(&owner)
+        .send();
+    assert!(format!("{:?}", result.unwrap_err()).contains("EventArchived"));
+    let result = program
+        .request()
+        .accounts(eventsnap::accounts::UpdateEvent {
+            event: event.event,
+            member_role: None,
+            authority: owner.pubkey(),
+        })
+        .args(eventsnap::instruction::UpdateEvent {
+            name: Some("Renamed".to_string()),
+            banner: None,
+            max_attendees: None,
+            max_images_per_user: None,
+            entry_fee_lamports: None,
+        })
+        .signer(&owner)
+        .send();
+    assert!(format!("{:?}", result.unwrap_err()).contains("EventArchived"));
+    let result = grant_role(&event, &owner, &bob.pubkey(), eventsnap::Role::Moderator, &client);
+    assert!(format!("{:?}", result.unwrap_err()).contains("EventArchived"));
+    let result = leave_event(&alice_join, &alice, &client, false, &[approval.highlight_page]);
+    assert!(format!("{:?}", result.unwrap_err()).contains("EventArchived"));
+
+    leave_event(&alice_join, &alice, &client, true, &[])
+        .expect("Attendees can still leave an archived event");
+    let result = program
+        .request()
+        .accounts(eventsnap::accounts::DeleteEvent {
+            event: event.event,
+            vault: vault_pda(&program_id, &event.event),
+            owner_user_data: user_data_pda(&program_id, &event.event, &owner.pubkey()),
+            program_data: program_accounts.program_data,
+            authority: owner.pubkey(),
+        })
+        .args(eventsnap::instruction::DeleteEvent {})
+        .signer(&owner)
+        .send();
+    assert!(format!("{:?}", result.unwrap_err()).contains("EventArchived"));
+
+    // ...but remain readable
+    let event_account: eventsnap::Event = program.account(event.event).unwrap();
+    assert!(event_account.status == eventsnap::EventStatus::Archived);
+    let attendee_page: eventsnap::AttendeePage = program.account(
+        attendee_page_pda(&program_id, &event.event, 0),
+    ).unwrap();
+    assert_eq!(attendee_page.attendees, vec![owner.pubkey()]);
+    let highlight_page: eventsnap::HighlightPage = program.account(approval.highlight_page).unwrap();
+    assert_eq!(highlight_page.highlights.len(), 1);
+
+    // Drafts count towards the total but stay shut until activated
+    let (total, active) = counts();
+    let draft = create_event_with_options(&program_accounts, &owner, &client, None, EventOptions {
+        draft: true,
+        ..Default::default()
+    }).expect("Failed to create draft event");
+    let event_account: eventsnap::Event = program.account(draft.event).unwrap();
+    assert!(event_account.status == eventsnap::EventStatus::Draft);
+    assert_eq!(counts(), (total + 1, active));
+    let result = join_event(&draft, &bob, &client);
+    assert!(format!("{:?}", result.err().unwrap()).contains("EventNotActive"));
+    set_event_status(&program_accounts, &draft, &owner, eventsnap::EventStatus::Active, &client)
+        .expect("Failed to activate draft event");
+    assert_eq!(counts(), (total + 1, active + 1));
+    join_event(&draft, &bob, &client).expect("Failed to join activated event");
+}
+
+#[test]
+fn test_paid_entry_and_withdrawal() {
+    let (owner, alice, bob, program_id, client) = setup_exclusive();
+    let program = client.program(program_id).unwrap();
+    let admin = admin_keypair();
+    let rpc = program.rpc();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    let set_protocol_fee = |signer: &Keypair, fee_bps| {
+        program
+            .request()
+            .accounts(eventsnap::accounts::UpdateProgramData {
+                program_data: program_accounts.program_data,
+                owner: signer.pubkey(),
+            })
+            .args(eventsnap::instruction::SetProtocolFee { fee_bps })
+            .signer(signer)
+            .send()
+    };
+    let result = set_protocol_fee(&alice, 500);
+    assert!(format!("{:?}", result.unwrap_err()).contains("UnauthorizedAdmin"));
+    let result = set_protocol_fee(&admin, 10_001);
+    assert!(format!("{:?}", result.unwrap_err()).contains("InvalidProtocolFee"));
+    set_protocol_fee(&admin, 500).expect("Failed to set protocol fee");
+
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+    let vault = vault_pda(&program_id, &event.event);
+    let entry_fee = 1_000_000;
+    program
+        .request()
+        .accounts(eventsnap::accounts::UpdateEvent {
+            event: event.event,
+            member_role: None,
+            authority: owner.pubkey(),
+        })
+        .args(eventsnap::instruction::UpdateEvent {
+            name: None,
+            banner: None,
+            max_attendees: None,
+            max_images_per_user: None,
+            entry_fee_lamports: Some(entry_fee),
+        })
+        .signer(&owner)
+        .send()
+        .expect("Failed to set entry fee");
+
+    // 5% goes to the protocol owner, the rest into the event vault
+    let protocol_fee = entry_fee * 500 / 10_000;
+    let admin_before = rpc.get_balance(&admin.pubkey()).unwrap();
+    let alice_before = rpc.get_balance(&alice.pubkey()).unwrap();
+    let vault_before = rpc.get_balance(&vault).unwrap();
+    join_event(&event, &alice, &client).expect("Failed to join paid event");
+
+    assert!(alice_before - rpc.get_balance(&alice.pubkey()).unwrap() >= entry_fee);
+    assert_eq!(rpc.get_balance(&vault).unwrap() - vault_before, entry_fee - protocol_fee);
+    // The admin wallet also pays the transaction fee
+    let admin_after = rpc.get_balance(&admin.pubkey()).unwrap();
+    assert!(admin_after <= admin_before + protocol_fee);
+    assert!(admin_after + 10_000 >= admin_before + protocol_fee);
+    let vault_account: eventsnap::EventVault = program.account(vault).unwrap();
+    assert_eq!(vault_account.collected, entry_fee - protocol_fee);
+
+    // Staff get in for free
+    grant_role(&event, &owner, &bob.pubkey(), eventsnap::Role::Photographer, &client)
+        .expect("Failed to grant role");
+    join_event(&event, &bob, &client).expect("Failed to join as photographer");
+    assert_eq!(rpc.get_balance(&vault).unwrap() - vault_before, entry_fee - protocol_fee);
+
+    let withdraw = |signer: &Keypair| {
+        program
+            .request()
+            .accounts(eventsnap::accounts::WithdrawProceeds {
+                event: event.event,
+                vault,
+                authority: signer.pubkey(),
+            })
+            .args(eventsnap::instruction::WithdrawProceeds {})
+            .signer(signer)
+            .send()
+    };
+    let result = withdraw(&alice);
+    assert!(format!("{:?}", result.unwrap_err()).contains("UnauthorizedWithdrawal"));
+    // Fees stay in escrow while the event is running
+    let result = withdraw(&owner);
+    assert!(format!("{:?}", result.unwrap_err()).contains("ProceedsLocked"));
+    set_event_status(&program_accounts, &event, &owner, eventsnap::EventStatus::Closed, &client)
+        .expect("Failed to close event");
+
+    let owner_before = rpc.get_balance(&owner.pubkey()).unwrap();
+    withdraw(&owner).expect("Failed to withdraw proceeds");
+    assert_eq!(rpc.get_balance(&owner.pubkey()).unwrap() - owner_before, entry_fee - protocol_fee);
+    assert_eq!(rpc.get_balance(&vault).unwrap(), vault_before);
+    let vault_account: eventsnap::EventVault = program.account(vault).unwrap();
+    assert_eq!(vault_account.withdrawn, entry_fee - protocol_fee);
+
+    let result = withdraw(&owner);
+    assert!(format!("{:?}", result.unwrap_err()).contains("NothingToWithdraw"));
+    // Alice could no longer be refunded, nor could anyone joining later
+    let result = cancel_event(&program_accounts, &event, &owner, &client);
+    assert!(format!("{:?}", result.unwrap_err()).contains("ProceedsAlreadyWithdrawn"));
+    let result = set_event_status(&program_accounts, &event, &owner, eventsnap::EventStatus::Active, &client);
+    assert!(format!("{:?}", result.unwrap_err()).contains("ProceedsAlreadyWithdrawn"));
+    let event_account: eventsnap::Event = program.account(event.event).unwrap();
+    let set_ends_at = |ends_at| {
+        program
+            .request()
+            .accounts(eventsnap::accounts::SetEventSchedule {
+                event: event.event,
+                vault,
+                member_role: None,
+                authority: owner.pubkey(),
+            })
+            .args(eventsnap::instruction::SetEventSchedule {
+                starts_at: event_account.starts_at,
+                ends_at,
+                uploads_close_at: event_account.uploads_close_at,
+            })
+            .signer(&owner)
+            .send()
+    };
+    let result = set_ends_at(event_account.ends_at - 60);
+    assert!(format!("{:?}", result.unwrap_err()).contains("ProceedsAlreadyWithdrawn"));
+    let result = set_ends_at(event_account.ends_at + 60);
+    assert!(format!("{:?}", result.unwrap_err()).contains("ProceedsAlreadyWithdrawn"));
+    set_ends_at(event_account.ends_at).expect("Other schedule changes are still allowed");
+
+    // Leave the shared ProgramData as the other tests expect it
+    set_protocol_fee(&admin, 0).expect("Failed to reset protocol fee");
+}
+
+#[test]
+fn test_token_entry_fees() {
+    let (owner, alice, bob, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+
+    for token_program in [spl_token::ID, spl_token_2022::ID] {
+        let fee = 5_000_000;
+        let mint = create_fee_mint(&client, &token_program, &[&owner, &alice, &bob], 3 * fee)
+            .expect("Failed to create fee mint");
+        let event = create_default_event(&program_accounts, &owner, &client)
+            .expect("Failed to create event");
+        let vault = vault_pda(&program_id, &event.event);
+        let event_tokens = token_account(&event.event, &mint, &token_program);
+        let owner_tokens = token_account(&owner.pubkey(), &mint, &token_program);
+        let alice_tokens = token_account(&alice.pubkey(), &mint, &token_program);
+        let bob_tokens = token_account(&bob.pubkey(), &mint, &token_program);
+
+        let result = set_token_fee(&event, &alice, &mint, &token_program, fee, &client);
+        assert!(format!("{:?}", result.unwrap_err()).contains("UnauthorizedEventUpdate"));
+        set_token_fee(&event, &owner, &mint, &token_program, fee, &client)
+            .expect("Failed to set token fee");
+        let event_account: eventsnap::Event = program.account(event.event).unwrap();
+        assert_eq!(event_account.fee_mint, Some(mint));
+        assert_eq!(event_account.token_entry_fee, fee);
+
+        let join_accounts = join_event(&event, &alice, &client).expect("Failed to join paid event");
+        assert_eq!(token_balance(&program, &alice_tokens), 2 * fee);
+        assert_eq!(token_balance(&program, &event_tokens), fee);
+        let user_data: eventsnap::UserData = program.account(join_accounts.user_data).unwrap();
+        assert_eq!(user_data.token_fee_paid, fee);
+        let vault_account: eventsnap::EventVault = program.account(vault).unwrap();
+        assert_eq!(vault_account.token_collected, fee);
+
+        // Attendees paid in this mint, so it can no longer be swapped out
+        let other_mint = create_fee_mint(&client, &token_program, &[], 0)
+            .expect("Failed to create fee mint");
+        let result = set_token_fee(&event, &owner, &other_mint, &token_program, fee, &client);
+        assert!(format!("{:?}", result.unwrap_err()).contains("FeeMintLocked"));
+
+        let withdraw = |signer: &Keypair, event: &EventAccounts, destination| {
+            program
+                .request()
+                .accounts(eventsnap::accounts::WithdrawTokenProceeds {
+                    event: event.event,
+                    vault: vault_pda(&program_id, &event.event),
+                    fee_mint: mint,
+                    event_token_account: token_account(&event.event, &mint, &token_program),
+                    destination,
+                    authority: signer.pubkey(),
+                    token_program,
+                })
+                .args(eventsnap::instruction::WithdrawTokenProceeds {})
+                .signer(signer)
+                .send()
+        };
+        let result = withdraw(&alice, &event, alice_tokens);
+        assert!(format!("{:?}", result.unwrap_err()).contains("UnauthorizedWithdrawal"));
+        // Fees stay in escrow while the event is running
+        let result = withdraw(&owner, &event, owner_tokens);
+        assert!(format!("{:?}", result.unwrap_err()).contains("ProceedsLocked"));
+        set_event_status(&program_accounts, &event, &owner, eventsnap::EventStatus::Closed, &client)
+            .expect("Failed to close event");
+        withdraw(&owner, &event, owner_tokens).expect("Failed to withdraw token proceeds");
+        assert_eq!(token_balance(&program, &owner_tokens), 4 * fee);
+        assert_eq!(token_balance(&program, &event_tokens), 0);
+        let vault_account: eventsnap::EventVault = program.account(vault).unwrap();
+        assert_eq!(vault_account.token_withdrawn, fee);
+        let result = withdraw(&owner, &event, owner_tokens);
+        assert!(format!("{:?}", result.unwrap_err()).contains("NothingToWithdraw"));
+        let result = cancel_event(&program_accounts, &event, &owner, &client);
+        assert!(format!("{:?}", result.unwrap_err()).contains("ProceedsAlreadyWithdrawn"));
+
+        // Once an event is cancelled, attendees take their fee back and the owner cannot
+        let refund_event = create_default_event(&program_accounts, &owner, &client)
+            .expect("Failed to create event");
+        let refund_event_tokens = token_account(&refund_event.event, &mint, &token_program);
+        set_token_fee(&refund_event, &owner, &mint, &token_program, fee, &client)
+            .expect("Failed to set token fee");
+        let bob_join = join_event(&refund_event, &bob, &client).expect("Failed to join paid event");
+        let result = claim_refund(&bob_join, &bob, &client);
+        assert!(format!("{:?}", result.unwrap_err()).contains("EventNotCancelled"));
+
+        cancel_event(&program_accounts, &refund_event, &owner, &client).expect("Failed to cancel event");
+        let result = withdraw(&owner, &refund_event, owner_tokens);
+        assert!(format!("{:?}", result.unwrap_err()).contains("EventCancelled"));
+
+        claim_refund(&bob_join, &bob, &client).expect("Failed to claim refund");
+        assert_eq!(token_balance(&program, &bob_tokens), 3 * fee);
+        assert_eq!(token_balance(&program, &refund_event_tokens), 0);
+        let vault_account: eventsnap::EventVault = program.account(vault_pda(&program_id, &refund_event.event)).unwrap();
+        assert_eq!(vault_account.token_refunded, fee);
+        assert!(program.rpc().get_account(&bob_join.user_data).is_err());
+    }
+}
+
+#[test]
+fn test_cancel_event_refunds() {
+    let (owner, alice, bob, program_id, client) = setup_exclusive();
+    let program = client.program(program_id).unwrap();
+    let rpc = program.rpc();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+    let vault = vault_pda(&program_id, &event.event);
+    let entry_fee = 1_000_000;
+    program
+        .request()
+        .accounts(eventsnap::accounts::UpdateEvent {
+            event: event.event,
+            member_role: None,
+            authority: owner.pubkey(),
+        })
+        .args(eventsnap::instruction::UpdateEvent {
+            name: None,
+            banner: None,
+            max_attendees: None,
+            max_images_per_user: None,
+            entry_fee_lamports: Some(entry_fee),
+        })
+        .signer(&owner)
+        .send()
+        .expect("Failed to set entry fee");
+
+    let alice_join = join_event(&event, &alice, &client).expect("Failed to join paid event");
+    let user_data: eventsnap::UserData = program.account(alice_join.user_data).unwrap();
+    assert_eq!(user_data.entry_fee_paid, entry_fee);
+    // Staff joined for free, so there is nothing to give back
+    grant_role(&event, &owner, &bob.pubkey(), eventsnap::Role::Moderator, &client)
+        .expect("Failed to grant role");
+    let bob_join = join_event(&event, &bob, &client).expect("Failed to join as moderator");
+
+    let result = cancel_event(&program_accounts, &event, &alice, &client);
+    assert!(format!("{:?}", result.unwrap_err()).contains("UnauthorizedEventUpdate"));
+    // Cancelling only goes through `cancel_event`
+    let result = set_event_status(&program_accounts, &event, &owner, eventsnap::EventStatus::Cancelled, &client);
+    assert!(format!("{:?}", result.unwrap_err()).contains("InvalidStatusTransition"));
+
+    let before: eventsnap::ProgramData = program.account(program_accounts.program_data).unwrap();
+    let tx = cancel_event(&program_accounts, &event, &owner, &client).expect("Failed to cancel event");
+    let after: eventsnap::ProgramData = program.account(program_accounts.program_data).unwrap();
+    assert_eq!(after.active_event_count, before.active_event_count - 1);
+    let changes = parse_events::<eventsnap::EventStatusChanged>(&client, program_id, &tx).unwrap();
+    assert!(changes[0].to == eventsnap::EventStatus::Cancelled);
+
+    let result = cancel_event(&program_accounts, &event, &owner, &client);
+    assert!(format!("{:?}", result.unwrap_err()).contains("EventCancelled"));
+    let result = set_event_status(&program_accounts, &event, &owner, eventsnap::EventStatus::Active, &client);
+    assert!(format!("{:?}", result.unwrap_err()).contains("InvalidStatusTransition"));
+
+    let result = program
+        .request()
+        .accounts(eventsnap::accounts::WithdrawProceeds {
+            event: event.event,
+            vault,
+            authority: owner.pubkey(),
+        })
+        .args(eventsnap::instruction::WithdrawProceeds {})
+        .signer(&owner)
+        .send();
+    assert!(format!("{:?}", result.unwrap_err()).contains("EventCancelled"));
+
+    let result = claim_refund(&bob_join, &bob, &client);
+    assert!(format!("{:?}", result.unwrap_err()).contains("NothingToRefund"));
+
+    // The owner cannot drop a paying attendee and keep their fee
+    let attendee_page = attendee_page_pda(&program_id, &event.event, 0);
+    let result = program
+        .request()
+        .accounts(eventsnap::accounts::RemoveAttendee {
+            event: event.event,
+            attendee_page,
+            tail_attendee_page: None,
+            user_data: alice_join.user_data,
+            attendee: alice.pubkey(),
+            member_role: None,
+            authority: owner.pubkey(),
+        })
+        .args(eventsnap::instruction::RemoveAttendee { keep_highlights: true })
+        .signer(&owner)
+        .send();
+    assert!(format!("{:?}", result.unwrap_err()).contains("RefundPending"));
+    let result = leave_event(&alice_join, &alice, &client, true, &[]);
+    assert!(format!("{:?}", result.unwrap_err()).contains("RefundPending"));
+    let delete = || {
+        program
+            .request()
+            .accounts(eventsnap::accounts::DeleteEvent {
+                event: event.event,
+                vault,
+                owner_user_data: user_data_pda(&program_id, &event.event, &owner.pubkey()),
+                program_data: program_accounts.program_data,
+                authority: owner.pubkey(),
+            })
+            .accounts(vec![AccountMeta::new(attendee_page, false)])
+            .args(eventsnap::instruction::DeleteEvent {})
+            .signer(&owner)
+            .send()
+    };
+    let result = delete();
+    assert!(format!("{:?}", result.unwrap_err()).contains("EventHasAttendees"));
+
+    let user_data_rent = rpc.get_balance(&alice_join.user_data).unwrap();
+    let alice_before = rpc.get_balance(&alice.pubkey()).unwrap();
+    let vault_before = rpc.get_balance(&vault).unwrap();
+    let tx = claim_refund(&alice_join, &alice, &client).expect("Failed to claim refund");
+    // The client wallet pays the transaction fee, so alice gets exactly the fee and her rent back
+    assert_eq!(rpc.get_balance(&alice.pubkey()).unwrap() - alice_before, entry_fee + user_data_rent);
+    assert_eq!(vault_before - rpc.get_balance(&vault).unwrap(), entry_fee);
+    assert!(rpc.get_account(&alice_join.user_data).is_err());
+    let vault_account: eventsnap::EventVault = program.account(vault).unwrap();
+    assert_eq!(vault_account.refunded, entry_fee);
+    let refunds = parse_events::<eventsnap::RefundClaimed>(&client, program_id, &tx).unwrap();
+    assert_eq!(refunds[0].lamports, entry_fee);
+    assert_eq!(refunds[0].attendee_count, 2);
+
+    let result = claim_refund(&alice_join, &alice, &client);
+    assert!(result.is_err());
+
+    // Staff owed nothing can still leave, after which the event can go
+    leave_event(&bob_join, &bob, &client, true, &[]).expect("Failed to leave cancelled event");
+    delete().expect("Failed to delete cancelled event");
+    assert!(program.rpc().get_account(&vault).is_err());
+}
+
+#[test]
+fn test_claim_badge() {
+    let (owner, alice, bob, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+    let rpc = program.rpc();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+    let alice_join = join_event(&event, &alice, &client).expect("Failed to join event");
+
+    let badge_mint = claim_badge(&alice_join, &alice, &client).expect("Failed to claim badge");
+    let badge_account = token_account(&alice.pubkey(), &badge_mint, &spl_token_2022::ID);
+    assert_eq!(token_balance(&program, &badge_account), 1);
+
+    let data = rpc.get_account_data(&badge_mint).unwrap();
+    let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data).unwrap();
+    assert_eq!(mint.base.supply, 1);
+    assert_eq!(mint.base.decimals, 0);
+    assert_eq!(mint.base.mint_authority, Some(event.event).into());
+    let metadata = mint.get_variable_len_extension::<TokenMetadata>().unwrap();
+    assert_eq!(metadata.name, event.name);
+    assert_eq!(metadata.symbol, eventsnap::BADGE_SYMBOL);
+    assert_eq!(metadata.uri, event.banner);
+    assert_eq!(
+        metadata.additional_metadata,
+        vec![(eventsnap::BADGE_UID_KEY.to_string(), event.uid.clone())]
+    );
+
+    // One badge per attendance
+    let result = claim_badge(&alice_join, &alice, &client);
+    assert!(result.is_err());
+    assert_eq!(token_balance(&program, &badge_account), 1);
+
+    // Only attendees get one
+    let bob_join = JoinEventAccounts {
+        user_data: user_data_pda(&program_id, &event.event, &bob.pubkey()),
+        event: event.event,
+        last_signature: String::new(),
+    };
+    let result = claim_badge(&bob_join, &bob, &client);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_mint_image() {
+    let (owner, alice, _, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+    let rpc = program.rpc();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+    let alice_join = join_event(&event, &alice, &client).expect("Failed to join event");
+    let upload = upload_image(&alice_join, &alice, &client, None).expect("Failed to upload image");
+    upload_image(&alice_join, &alice, &client, None).expect("Failed to upload image");
+    approve_image(&program_accounts, &upload, 0, &client).expect("Failed to approve image");
+
+    // Minting is opt-in per event
+    let result = mint_image(&alice_join, &alice, 0, &client);
+    assert!(result.is_err());
+
+    let result = create_photo_collection(&event, &alice, 500, 70, &client);
+    assert!(format!("{:?}", result.unwrap_err()).contains("UnauthorizedEventUpdate"));
+    let result = create_photo_collection(&event, &owner, 10_001, 70, &client);
+    assert!(format!("{:?}", result.unwrap_err()).contains("InvalidRoyalty"));
+    let result = create_photo_collection(&event, &owner, 500, 101, &client);
+    assert!(format!("{:?}", result.unwrap_err()).contains("InvalidRoyalty"));
+    let collection = create_photo_collection(&event, &owner, 500, 70, &client)
+        .expect("Failed to create photo collection");
+
+    let result = mint_image(&alice_join, &alice, 1, &client);
+    assert!(format!("{:?}", result.unwrap_err()).contains("ImageNotApproved"));
+
+    let image_mint = mint_image(&alice_join, &alice, 0, &client).expect("Failed to mint image");
+    let image_account = token_account(&alice.pubkey(), &image_mint, &spl_token_2022::ID);
+    assert_eq!(token_balance(&program, &image_account), 1);
+    let user_data: eventsnap::UserData = program.account(alice_join.user_data).unwrap();
+    assert!(user_data.images[0].minted);
+    assert!(!user_data.images[1].minted);
+
+    let data = rpc.get_account_data(&image_mint).unwrap();
+    let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data).unwrap();
+    assert_eq!(mint.base.supply, 1);
+    assert_eq!(mint.base.mint_authority, Some(event.event).into());
+    let metadata = mint.get_variable_len_extension::<TokenMetadata>().unwrap();
+    assert_eq!(metadata.uri, upload.url);
+    assert_eq!(metadata.symbol, eventsnap::PHOTO_SYMBOL);
+    assert_eq!(
+        metadata.additional_metadata,
+        vec![
+            (eventsnap::BADGE_UID_KEY.to_string(), event.uid.clone()),
+            (eventsnap::COLLECTION_KEY.to_string(), collection.to_string()),
+            (eventsnap::ROYALTY_BPS_KEY.to_string(), "500".to_string()),
+            (alice.pubkey().to_string(), "70".to_string()),
+            (owner.pubkey().to_string(), "30".to_string()),
+            (
+                eventsnap::CONTENT_HASH_KEY.to_string(),
+                upload.content_hash.iter().map(|byte| format!("{:02x}", byte)).collect(),
+            ),
+        ]
+    );
+
+    // Wallets see the photo as a member of the collection group
+    let member_pointer = mint.get_extension::<GroupMemberPointer>().unwrap();
+    assert_eq!(Option::<Pubkey>::from(member_pointer.member_address), Some(image_mint));
+    assert!(mint.get_extension_types().unwrap().contains(&ExtensionType::TokenGroupMember));
+    let collection_data = rpc.get_account_data(&collection).unwrap();
+    let collection_mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&collection_data).unwrap();
+    let group_pointer = collection_mint.get_extension::<GroupPointer>().unwrap();
+    assert_eq!(Option::<Pubkey>::from(group_pointer.group_address), Some(collection));
+    assert!(collection_mint.get_extension_types().unwrap().contains(&ExtensionType::TokenGroup));
+
+    let result = mint_image(&alice_join, &alice, 0, &client);
+    assert!(format!("{:?}", result.unwrap_err()).contains("ImageAlreadyMinted"));
+}
+
+#[test]
+fn test_image_tree() {
+    let (owner, alice, _, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+    let alice_join = join_event(&event, &alice, &client).expect("Failed to join event");
+
+    let result = create_image_tree(&event, &alice, 3, 2, &client);
+    assert!(format!("{:?}", result.unwrap_err()).contains("UnauthorizedEventUpdate"));
+    let result = create_image_tree(&event, &owner, 0, 2, &client);
+    assert!(format!("{:?}", result.unwrap_err()).contains("InvalidImageTreeConfig"));
+    let image_tree = create_image_tree(&event, &owner, 3, 2, &client)
+        .expect("Failed to create image tree");
+    assert_eq!(image_tree, image_tree_pda(&program_id, &event.event));
+
+    let result = upload_image(&alice_join, &alice, &client, None);
+    assert!(format!("{:?}", result.unwrap_err()).contains("ImagesStoredInTree"));
+
+    let image = |n: u8| (
+        eventsnap::StorageRef::Http(format!("https://example.com/{}.jpg", n)),
+        format!("tag{}", n),
+        [n + 1; 32],
+    );
+    let mut leaves = vec![];
+    let mut append = |n: u8| {
+        let (storage, tag, content_hash) = image(n);
+        let tx = append_image(&alice_join, &alice, &storage, &tag, content_hash, &client)?;
+        leaves.push(eventsnap::image_leaf(&storage.uri(), &tag, &alice.pubkey(), &content_hash));
+        Ok::<_, Box<dyn std::error::Error>>((tx, leaves.clone()))
+    };
+
+    append(0).expect("Failed to append image");
+    let (_, two_leaves) = append(1).expect("Failed to append image");
+    let (tx, three_leaves) = append(2).expect("Failed to append image");
+    let (root, _) = image_tree_proof(&three_leaves, 3, 0);
+    let tree: eventsnap::ImageTree = program.account(image_tree).unwrap();
+    assert_eq!(tree.root(), root);
+    assert_eq!(tree.sequence_number, 3);
+    assert_eq!(tree.leaf_count, 3);
+    let appended = parse_events::<eventsnap::ImageAppended>(&client, program_id, &tx).unwrap();
+    assert_eq!(appended[0].leaf_index, 2);
+    assert_eq!(appended[0].root, root);
+
+    let verify = |leaf_index: u32, n: u8, proof: Vec<[u8; 32]>| {
+        let (storage, tag, content_hash) = image(n);
+        program
+            .request()
+            .accounts(eventsnap::accounts::VerifyImage { image_tree })
+            .args(eventsnap::instruction::VerifyImage {
+                leaf_index,
+                storage,
+                tag,
+                uploader: alice.pubkey(),
+                content_hash,
+                proof,
+            })
+            .send()
+    };
+    let (_, proof) = image_tree_proof(&three_leaves, 3, 0);
+    verify(0, 0, proof.clone()).expect("Failed to verify image");
+    let result = verify(0, 1, proof.clone());
+    assert!(format!("{:?}", result.unwrap_err()).contains("InvalidImageProof"));
+    let result = verify(1, 0, proof.clone());
+    assert!(format!("{:?}", result.unwrap_err()).contains("InvalidImageProof"));
+    program
+        .request()
+        .accounts(eventsnap::accounts::VerifyImage { image_tree })
+        .args(eventsnap::instruction::VerifyImageLeaf { leaf_index: 0, leaf: three_leaves[0], proof })
+        .send()
+        .expect("Failed to verify image leaf");
+
+    // A proof from one append ago is still in the changelog; two ago is not
+    let (_, stale_proof) = image_tree_proof(&two_leaves, 3, 1);
+    verify(1, 1, stale_proof.clone()).expect("Failed to verify against a recent root");
+    append(3).expect("Failed to append image");
+    let result = verify(1, 1, stale_proof);
+    assert!(format!("{:?}", result.unwrap_err()).contains("InvalidImageProof"));
+
+    for n in 4..8 {
+        append(n).expect("Failed to append image");
+    }
+    let result = append(8);
+    assert!(format!("{:?}", result.unwrap_err()).contains("ImageTreeFull"));
+    let user_data: eventsnap::UserData = program.account(alice_join.user_data).unwrap();
+    assert_eq!(user_data.tree_image_count, 8);
+    assert!(user_data.images.is_empty());
+}
+
+#[test]
+fn test_image_storage_refs() {
+    let (owner, alice, _, program_id, client) = setup();
+    let program = client.program(program_id).unwrap();
+
+    let program_accounts = initialize_program(program_id, &client)
+        .expect("Failed to initialize program");
+    let event = create_default_event(&program_accounts, &owner, &client)
+        .expect("Failed to create event");
+    let alice_join = join_event(&event, &alice, &client).expect("Failed to join event");
+
+    let drive = Keypair::new().pubkey();
+    let valid = vec![
+        (
+            eventsnap::StorageRef::Ipfs("QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG".to_string()),
+            "ipfs://QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG".to_string(),
+        ),
+        (
+            eventsnap::StorageRef::Ipfs("bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi".to_string()),
+            "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi".to_string(),
+        ),
+        (
+            eventsnap::StorageRef::Arweave("bNbA3TEQVL60xlgCcqdz4ZPHFZ711cZ3hmkpGttDt_U".to_string()),
+            "ar://bNbA3TEQVL60xlgCcqdz4ZPHFZ711cZ3hmkpGttDt_U".to_string(),
+        ),
+        (
+            eventsnap::StorageRef::ShadowDrive { account: drive, path: "photos/1.jpg".to_string() },
+            format!("https://shdw-drive.genesysgo.net/{}/photos/1.jpg", drive),
+        ),
+    ];
+    let mut uploads = vec![];
+    for (i, (storage, uri)) in valid.iter().enumerate() {
+        let upload = upload_image_with_storage(
+            &alice_join,
+            &alice,
+            storage.clone(),
+            [i as u8 + 1; 32],
+            "storage".to_string(),
+            &client,
+        ).expect("Failed to upload image");
+        assert_eq!(&upload.url, uri);
+        uploads.push(upload);
+    }
+    let user_data: eventsnap::UserData = program.account(alice_join.user_data).unwrap();
+    assert_eq!(user_data.images.len(), valid.len());
+    for (i, (image, (storage, _))) in user_data.images.iter().zip(&valid).enumerate() {
+        assert!(image.storage == *storage);
+        assert_eq!(image.content_hash, [i as u8 + 1; 32]);
+    }
+
+    let approval = approve_image(&program_accounts, &uploads[2], 2, &client)
+        .expect("Failed to approve image");
+    let highlight_page: eventsnap::HighlightPage = program.account(approval.highlight_page).unwrap();
+    assert_eq!(highlight_page.highlights[0].url, uploads[2].url);
+    assert_eq!(highlight_page.highlights[0].content_hash, [3; 32]);
+
+    let invalid = vec![
+        (eventsnap::StorageRef::Http("ftp://example.com/a.jpg".to_string()), "InvalidHttpUrl"),
+        (eventsnap::StorageRef::Http("https://example.com/a b.jpg".to_string()), "InvalidHttpUrl"),
+        (eventsnap::StorageRef::Ipfs("Qm0wAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG".to_string()), "InvalidIpfsCid"),
+        (eventsnap::StorageRef::Ipfs("bafyBEIG".to_string()), "InvalidIpfsCid"),
+        (eventsnap::StorageRef::Arweave("bNbA3TEQVL60xlgCcqdz4ZPHFZ711cZ3hmkpGttDt".to_string()), "InvalidArweaveTxId"),
+        (eventsnap::StorageRef::Arweave("bNbA3TEQVL60xlgCcqdz4ZPHFZ711cZ3hmkpGttD/_U".to_string()), "InvalidArweaveTxId"),
+        (eventsnap::StorageRef::ShadowDrive { account: drive, path: "/photos/1.jpg".to_string() }, "InvalidShadowDrivePath"),
+        (eventsnap::StorageRef::ShadowDrive { account: drive, path: "photos/../1.jpg".to_string() }, "InvalidShadowDrivePath"),
+        (eventsnap::StorageRef::ShadowDrive { account: drive, path: "a".repeat(150) }, "UrlTooLong"),
+    ];
+    for (storage, error) in invalid {
+        let result = upload_image_with_storage(&alice_join, &alice, storage, [1; 32], "bad".to_string(), &client);
+        assert!(format!("{:?}", result.unwrap_err()).contains(error), "expected {}", error);
+    }
+
+    let result = upload_image_with_storage(
+        &alice_join,
+        &alice,
+        eventsnap::StorageRef::Http("https://example.com/unhashed.jpg".to_string()),
+        [0; 32],
+        "bad".to_string(),
+        &client,
+    );
+    assert!(format!("{:?}", result.unwrap_err()).contains("MissingContentHash"));
+}
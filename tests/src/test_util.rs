@@ -1,19 +1,28 @@
-use std::{process::Command, str::FromStr, sync::Arc};
+use base64::Engine;
+use std::{cell::RefCell, process::Command, str::FromStr, sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard}};
 use anchor_client::{
-    anchor_lang, solana_client::rpc_client::RpcClient, solana_sdk::{
-        commitment_config::CommitmentConfig, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signature::{read_keypair_file, Keypair}, signer::Signer
-    }, Client, Cluster
+    anchor_lang, solana_client::{rpc_client::RpcClient, rpc_config::RpcTransactionConfig}, solana_sdk::{
+        commitment_config::CommitmentConfig, ed25519_program, hash::hash, program_pack::Pack, instruction::{AccountMeta, Instruction}, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signature::{read_keypair_file, Keypair, Signature}, signer::Signer, sysvar
+    }, Client, Cluster, Program
 };
+use anchor_spl::{
+    associated_token::{get_associated_token_address_with_program_id, spl_associated_token_account},
+    token::spl_token,
+    token_2022::spl_token_2022,
+};
+
+pub const DEFAULT_MAX_ATTENDEES: u32 = 10;
+pub const DEFAULT_MAX_IMAGES_PER_USER: u32 = 20;
 
 pub struct ProgramAccounts {
-    pub program_data: Keypair,
+    pub program_data: Pubkey,
     pub oracle: Pubkey,
     pub program_id: Pubkey,
     pub last_signature: String,
 }
 
 pub struct EventAccounts {
-    pub event: Keypair,
+    pub event: Pubkey,
     pub uid: String,
     pub name: String,
     pub banner: String,
@@ -21,11 +30,190 @@ pub struct EventAccounts {
 }
 
 pub struct JoinEventAccounts {
-    pub user_data: Keypair,
+    pub user_data: Pubkey,
     pub event: Pubkey,
     pub last_signature: String,
 }
 
+pub fn program_data_pda(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[eventsnap::PROGRAM_DATA_SEED], program_id).0
+}
+
+pub fn event_pda(program_id: &Pubkey, uid: &str) -> Pubkey {
+    Pubkey::find_program_address(&[eventsnap::EVENT_SEED, uid.as_bytes()], program_id).0
+}
+
+pub fn user_data_pda(program_id: &Pubkey, event: &Pubkey, authority: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[eventsnap::USER_SEED, event.as_ref(), authority.as_ref()],
+        program_id,
+    ).0
+}
+
+pub fn attendee_page_pda(program_id: &Pubkey, event: &Pubkey, page_no: u32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[eventsnap::ATTENDEES_SEED, event.as_ref(), &page_no.to_le_bytes()],
+        program_id,
+    ).0
+}
+
+pub fn highlight_page_pda(program_id: &Pubkey, event: &Pubkey, page_no: u32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[eventsnap::HIGHLIGHTS_SEED, event.as_ref(), &page_no.to_le_bytes()],
+        program_id,
+    ).0
+}
+
+pub fn member_role_pda(program_id: &Pubkey, event: &Pubkey, member: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[eventsnap::ROLE_SEED, event.as_ref(), member.as_ref()],
+        program_id,
+    ).0
+}
+
+// Role PDA to pass as the optional `member_role` account, if one has been granted
+pub fn member_role_account(program: &Program<Arc<Keypair>>, event: &Pubkey, member: &Pubkey) -> Option<Pubkey> {
+    let member_role = member_role_pda(&program.id(), event, member);
+    program.rpc().get_account(&member_role).ok().map(|_| member_role)
+}
+
+pub fn vault_pda(program_id: &Pubkey, event: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[eventsnap::VAULT_SEED, event.as_ref()], program_id).0
+}
+
+pub fn ban_pda(program_id: &Pubkey, event: &Pubkey, member: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[eventsnap::BAN_SEED, event.as_ref(), member.as_ref()],
+        program_id,
+    ).0
+}
+
+/// The associated token account of `owner` for `mint` under `token_program`.
+pub fn token_account(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(owner, mint, token_program)
+}
+
+pub fn token_balance(program: &Program<Arc<Keypair>>, account: &Pubkey) -> u64 {
+    program.rpc().get_token_account_balance(account).unwrap().amount.parse().unwrap()
+}
+
+/// Creates a fresh mint under `token_program` (classic Token or Token-2022)
+/// and gives each holder an associated token account with `amount` in it.
+pub fn create_fee_mint(
+    client: &Client<Arc<Keypair>>,
+    token_program: &Pubkey,
+    holders: &[&Keypair],
+    amount: u64,
+) -> Result<Pubkey, Box<dyn std::error::Error>> {
+    let program_id = Pubkey::from_str("9B1F56Dx649qbEDRbQAXZtmPXTFrLaYjTXBuCeZWMJ1x")?;
+    let program = client.program(program_id)?;
+    let admin = admin_keypair();
+    let mint = Keypair::new();
+    let rent = program.rpc().get_minimum_balance_for_rent_exemption(spl_token::state::Mint::LEN)?;
+
+    let mut request = program
+        .request()
+        .instruction(anchor_lang::solana_program::system_instruction::create_account(
+            &admin.pubkey(),
+            &mint.pubkey(),
+            rent,
+            spl_token::state::Mint::LEN as u64,
+            token_program,
+        ))
+        .instruction(spl_token_2022::instruction::initialize_mint2(
+            token_program,
+            &mint.pubkey(),
+            &admin.pubkey(),
+            None,
+            6,
+        )?);
+    for holder in holders {
+        request = request
+            .instruction(spl_associated_token_account::instruction::create_associated_token_account(
+                &admin.pubkey(),
+                &holder.pubkey(),
+                &mint.pubkey(),
+                token_program,
+            ))
+            .instruction(spl_token_2022::instruction::mint_to(
+                token_program,
+                &mint.pubkey(),
+                &token_account(&holder.pubkey(), &mint.pubkey(), token_program),
+                &admin.pubkey(),
+                &[],
+                amount,
+            )?);
+    }
+    let tx = request.signer(&admin).signer(&mint).send()?;
+
+    println!("Create fee mint transaction signature: {}", tx);
+
+    Ok(mint.pubkey())
+}
+
+pub fn set_token_fee(
+    event_accounts: &EventAccounts,
+    authority: &Keypair,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    amount: u64,
+    client: &Client<Arc<Keypair>>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let program_id = Pubkey::from_str("9B1F56Dx649qbEDRbQAXZtmPXTFrLaYjTXBuCeZWMJ1x")?;
+    let program = client.program(program_id)?;
+
+    let tx = program
+        .request()
+        .accounts(eventsnap::accounts::SetTokenFee {
+            event: event_accounts.event,
+            member_role: member_role_account(&program, &event_accounts.event, &authority.pubkey()),
+            vault: vault_pda(&program_id, &event_accounts.event),
+            fee_mint: *mint,
+            event_token_account: token_account(&event_accounts.event, mint, token_program),
+            authority: authority.pubkey(),
+            token_program: *token_program,
+            associated_token_program: spl_associated_token_account::ID,
+            system_program: anchor_lang::solana_program::system_program::ID,
+        })
+        .args(eventsnap::instruction::SetTokenFee { amount })
+        .signer(authority)
+        .send()?;
+
+    println!("Set token fee transaction signature: {}", tx);
+
+    Ok(tx.to_string())
+}
+
+pub fn badge_mint_pda(program_id: &Pubkey, user_data: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[eventsnap::BADGE_SEED, user_data.as_ref()], program_id).0
+}
+
+pub fn collection_mint_pda(program_id: &Pubkey, event: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[eventsnap::COLLECTION_SEED, event.as_ref()], program_id).0
+}
+
+pub fn image_tree_pda(program_id: &Pubkey, event: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[eventsnap::IMAGE_TREE_SEED, event.as_ref()], program_id).0
+}
+
+// Off-chain mirror of an event's image tree: the root over `leaves` padded
+// with empty leaves, and the sibling path of leaf `index`
+pub fn image_tree_proof(leaves: &[[u8; 32]], max_depth: u8, index: usize) -> ([u8; 32], Vec<[u8; 32]>) {
+    let mut level = leaves.to_vec();
+    level.resize(1 << max_depth, [0; 32]);
+    let mut position = index;
+    let mut proof = vec![];
+    while level.len() > 1 {
+        proof.push(level[position ^ 1]);
+        level = level
+            .chunks(2)
+            .map(|pair| eventsnap::image_tree_node(&pair[0], &pair[1]))
+            .collect();
+        position /= 2;
+    }
+    (level[0], proof)
+}
+
 pub fn request_airdrop_with_retries(rpc_client: &RpcClient, pubkey: &Pubkey, amount: u64) -> Result<(), String> {
     let max_retries = 5;
     let mut current_try = 0;
@@ -99,7 +287,45 @@ pub fn ensure_test_validator() -> RpcClient {
     rpc_client
 }
 
+/// ProgramData is a singleton shared by every test. Tests that change it for
+/// everyone (rotating keys, pausing) hold this lock exclusively; all others
+/// hold it shared until the test thread exits.
+static PROGRAM_DATA_LOCK: RwLock<()> = RwLock::new(());
+
+enum ProgramDataGuard {
+    Shared(#[allow(dead_code)] RwLockReadGuard<'static, ()>),
+    Exclusive(#[allow(dead_code)] RwLockWriteGuard<'static, ()>),
+}
+
+thread_local! {
+    static PROGRAM_DATA_GUARD: RefCell<Option<ProgramDataGuard>> = const { RefCell::new(None) };
+}
+
+fn hold_program_data_lock(exclusive: bool) {
+    PROGRAM_DATA_GUARD.with(|guard| {
+        // Release the guard of a previous test run on this thread first
+        guard.borrow_mut().take();
+        let new_guard = if exclusive {
+            ProgramDataGuard::Exclusive(PROGRAM_DATA_LOCK.write().unwrap_or_else(PoisonError::into_inner))
+        } else {
+            ProgramDataGuard::Shared(PROGRAM_DATA_LOCK.read().unwrap_or_else(PoisonError::into_inner))
+        };
+        *guard.borrow_mut() = Some(new_guard);
+    });
+}
+
 pub fn setup() -> (Keypair, Keypair, Keypair, Pubkey, Client<Arc<Keypair>>) {
+    hold_program_data_lock(false);
+    setup_accounts()
+}
+
+/// Like `setup`, for tests that change ProgramData in ways other tests would observe.
+pub fn setup_exclusive() -> (Keypair, Keypair, Keypair, Pubkey, Client<Arc<Keypair>>) {
+    hold_program_data_lock(true);
+    setup_accounts()
+}
+
+fn setup_accounts() -> (Keypair, Keypair, Keypair, Pubkey, Client<Arc<Keypair>>) {
     let program_id = "9B1F56Dx649qbEDRbQAXZtmPXTFrLaYjTXBuCeZWMJ1x"; // Your program ID
     let anchor_wallet = std::env::var("ANCHOR_WALLET").unwrap();
     let payer = Arc::new(read_keypair_file(&anchor_wallet).unwrap());
@@ -127,32 +353,66 @@ pub fn setup() -> (Keypair, Keypair, Keypair, Pubkey, Client<Arc<Keypair>>) {
     (owner, alice, bob, program_id, client)
 }
 
+/// Owner of the singleton ProgramData; the funded test wallet, so every test
+/// knows it regardless of which one initialized the program.
+pub fn admin_keypair() -> Keypair {
+    read_keypair_file(std::env::var("ANCHOR_WALLET").unwrap()).unwrap()
+}
+
+/// The moderation oracle used by every test; the funded test wallet, so it can
+/// pay for new highlight pages.
+pub fn oracle_keypair() -> Keypair {
+    read_keypair_file(std::env::var("ANCHOR_WALLET").unwrap()).unwrap()
+}
+
 pub fn initialize_program(
-    owner: &Keypair,
     program_id: Pubkey,
     client: &Client<Arc<Keypair>>,
 ) -> Result<ProgramAccounts, Box<dyn std::error::Error>> {
     let program = client.program(program_id)?;
-    let program_data = Keypair::new();
-    let oracle = Pubkey::new_unique();
+    let program_data = program_data_pda(&program_id);
     let system_program = anchor_lang::solana_program::system_program::ID;
 
-    let tx = program
+    // ProgramData is a singleton, so only the first test to run initializes it
+    if let Ok(existing) = program.account::<eventsnap::ProgramData>(program_data) {
+        return Ok(ProgramAccounts {
+            program_data,
+            oracle: existing.oracle,
+            program_id,
+            last_signature: String::new(),
+        });
+    }
+
+    let owner = admin_keypair();
+    let oracle = oracle_keypair().pubkey();
+    let tx = match program
         .request()
         .accounts(eventsnap::accounts::Initialize {
-            program_data: program_data.pubkey(),
+            program_data,
+            program: program_id,
+            program_upgrade_data: Pubkey::find_program_address(
+                &[program_id.as_ref()],
+                &anchor_lang::solana_program::bpf_loader_upgradeable::ID,
+            ).0,
             owner: owner.pubkey(),
             system_program,
         })
         .args(eventsnap::instruction::Initialize { oracle })
-        .signer(&program_data)
-        .signer(owner)
-        .send()?;
+        .signer(&owner)
+        .send()
+    {
+        Ok(tx) => tx,
+        // Another test may have initialized it concurrently
+        Err(_) if program.account::<eventsnap::ProgramData>(program_data).is_ok() => {
+            return initialize_program(program_id, client);
+        }
+        Err(e) => return Err(e.into()),
+    };
 
     println!("Program initialization signature: {}", tx);
 
     Ok(ProgramAccounts {
-        program_data,  // Move ownership to the struct
+        program_data,
         oracle,
         program_id,
         last_signature: tx.to_string(),
@@ -164,9 +424,64 @@ pub fn create_event(
     authority: &Keypair,
     client: &Client<Arc<Keypair>>,
     event_params: Option<(String, String, String)>,
+) -> Result<EventAccounts, Box<dyn std::error::Error>> {
+    create_event_with_limits(
+        program_accounts,
+        authority,
+        client,
+        event_params,
+        DEFAULT_MAX_ATTENDEES,
+        DEFAULT_MAX_IMAGES_PER_USER,
+    )
+}
+
+/// Optional `create_event` arguments, defaulting to an event that is open
+/// for joining and uploads indefinitely.
+pub struct EventOptions {
+    pub max_attendees: u32,
+    pub max_images_per_user: u32,
+    pub starts_at: i64,
+    pub ends_at: i64,
+    pub uploads_close_at: i64,
+    pub draft: bool,
+}
+
+impl Default for EventOptions {
+    fn default() -> Self {
+        Self {
+            max_attendees: DEFAULT_MAX_ATTENDEES,
+            max_images_per_user: DEFAULT_MAX_IMAGES_PER_USER,
+            starts_at: 0,
+            ends_at: i64::MAX,
+            uploads_close_at: i64::MAX,
+            draft: false,
+        }
+    }
+}
+
+pub fn create_event_with_limits(
+    program_accounts: &ProgramAccounts,
+    authority: &Keypair,
+    client: &Client<Arc<Keypair>>,
+    event_params: Option<(String, String, String)>,
+    max_attendees: u32,
+    max_images_per_user: u32,
+) -> Result<EventAccounts, Box<dyn std::error::Error>> {
+    create_event_with_options(program_accounts, authority, client, event_params, EventOptions {
+        max_attendees,
+        max_images_per_user,
+        ..Default::default()
+    })
+}
+
+pub fn create_event_with_options(
+    program_accounts: &ProgramAccounts,
+    authority: &Keypair,
+    client: &Client<Arc<Keypair>>,
+    event_params: Option<(String, String, String)>,
+    options: EventOptions,
 ) -> Result<EventAccounts, Box<dyn std::error::Error>> {
     let program = client.program(program_accounts.program_id)?;
-    let event = Keypair::new();
     let system_program = anchor_lang::solana_program::system_program::ID;
 
     // Use provided parameters or defaults
@@ -175,12 +490,15 @@ pub fn create_event(
         "Test Event".to_string(),
         "https://example.com/banner.jpg".to_string(),
     ));
+    let event = event_pda(&program_accounts.program_id, &uid);
 
     let tx = program
         .request()
         .accounts(eventsnap::accounts::CreateEvent {
-            event: event.pubkey(),
-            program_data: program_accounts.program_data.pubkey(),
+            event,
+            attendee_page: attendee_page_pda(&program_accounts.program_id, &event, 0),
+            vault: vault_pda(&program_accounts.program_id, &event),
+            program_data: program_accounts.program_data,
             authority: authority.pubkey(),
             system_program,
         })
@@ -188,8 +506,13 @@ pub fn create_event(
             uid: uid.clone(),
             name: name.clone(),
             banner: banner.clone(),
+            max_attendees: options.max_attendees,
+            max_images_per_user: options.max_images_per_user,
+            starts_at: options.starts_at,
+            ends_at: options.ends_at,
+            uploads_close_at: options.uploads_close_at,
+            draft: options.draft,
         })
-        .signer(&event)
         .signer(authority)
         .send()?;
 
@@ -218,20 +541,93 @@ pub fn join_event(
     authority: &Keypair,
     client: &Client<Arc<Keypair>>,
 ) -> Result<JoinEventAccounts, Box<dyn std::error::Error>> {
-    let program = client.program(Pubkey::from_str("9B1F56Dx649qbEDRbQAXZtmPXTFrLaYjTXBuCeZWMJ1x")?)?;
-    let user_data = Keypair::new();
+    join_event_with_access(event_accounts, authority, client, vec![], None)
+}
+
+// Ed25519 program instruction checking `signer`'s signature over `message`
+pub fn ed25519_instruction(signer: &Keypair, message: &[u8]) -> Instruction {
+    const PUBLIC_KEY_OFFSET: u16 = 16;
+    const SIGNATURE_OFFSET: u16 = PUBLIC_KEY_OFFSET + 32;
+    const MESSAGE_OFFSET: u16 = SIGNATURE_OFFSET + 64;
+
+    let mut data = vec![1, 0];
+    for offset in [
+        SIGNATURE_OFFSET,
+        u16::MAX,
+        PUBLIC_KEY_OFFSET,
+        u16::MAX,
+        MESSAGE_OFFSET,
+        message.len() as u16,
+        u16::MAX,
+    ] {
+        data.extend_from_slice(&offset.to_le_bytes());
+    }
+    data.extend_from_slice(signer.pubkey().as_ref());
+    data.extend_from_slice(signer.sign_message(message).as_ref());
+    data.extend_from_slice(message);
+
+    Instruction {
+        program_id: ed25519_program::id(),
+        accounts: vec![],
+        data,
+    }
+}
+
+// Joins with an allowlist `proof` and/or an invite signed by `inviter`
+pub fn join_event_with_access(
+    event_accounts: &EventAccounts,
+    authority: &Keypair,
+    client: &Client<Arc<Keypair>>,
+    proof: Vec<[u8; 32]>,
+    inviter: Option<&Keypair>,
+) -> Result<JoinEventAccounts, Box<dyn std::error::Error>> {
+    let program_id = Pubkey::from_str("9B1F56Dx649qbEDRbQAXZtmPXTFrLaYjTXBuCeZWMJ1x")?;
+    let program = client.program(program_id)?;
+    let user_data = user_data_pda(&program_id, &event_accounts.event, &authority.pubkey());
     let system_program = anchor_lang::solana_program::system_program::ID;
 
-    let tx = program
-        .request()
+    let event: eventsnap::Event = program.account(event_accounts.event)?;
+    let attendee_page = attendee_page_pda(&program_id, &event_accounts.event, event.next_attendee_page());
+    let program_data: eventsnap::ProgramData = program.account(program_data_pda(&program_id))?;
+    // Token fee accounts, under whichever token program owns the mint
+    let token_accounts = match event.fee_mint {
+        Some(mint) => {
+            let token_program = program.rpc().get_account(&mint)?.owner;
+            Some((
+                mint,
+                token_account(&authority.pubkey(), &mint, &token_program),
+                token_account(&event_accounts.event, &mint, &token_program),
+                token_program,
+            ))
+        }
+        None => None,
+    };
+
+    let mut request = program.request();
+    if let Some(inviter) = inviter {
+        let message = eventsnap::invite_message(&event_accounts.event, &authority.pubkey());
+        request = request.instruction(ed25519_instruction(inviter, &message));
+    }
+
+    let tx = request
         .accounts(eventsnap::accounts::JoinEvent {
-            event: event_accounts.event.pubkey(),
-            user_data: user_data.pubkey(),
+            program_data: program_data_pda(&program_id),
+            event: event_accounts.event,
+            attendee_page,
+            user_data,
+            vault: vault_pda(&program_id, &event_accounts.event),
+            protocol_fee_recipient: program_data.owner,
+            member_role: member_role_account(&program, &event_accounts.event, &authority.pubkey()),
+            ban: ban_pda(&program_id, &event_accounts.event, &authority.pubkey()),
+            instructions: inviter.map(|_| sysvar::instructions::ID),
+            fee_mint: token_accounts.map(|accounts| accounts.0),
+            payer_token_account: token_accounts.map(|accounts| accounts.1),
+            event_token_account: token_accounts.map(|accounts| accounts.2),
+            token_program: token_accounts.map(|accounts| accounts.3),
             authority: authority.pubkey(),
             system_program,
         })
-        .args(eventsnap::instruction::JoinEvent {})
-        .signer(&user_data)
+        .args(eventsnap::instruction::JoinEvent { proof })
         .signer(authority)
         .send()?;
 
@@ -239,13 +635,14 @@ pub fn join_event(
 
     Ok(JoinEventAccounts {
         user_data,
-        event: event_accounts.event.pubkey(),
+        event: event_accounts.event,
         last_signature: tx.to_string(),
     })
 }
 
 pub struct ImageUploadAccounts {
     pub url: String,
+    pub content_hash: [u8; 32],
     pub tag: String,
     pub event: Pubkey,
     pub user_data: Pubkey,
@@ -259,22 +656,49 @@ pub fn upload_image(
     image_params: Option<(String, String)>,
 ) -> Result<ImageUploadAccounts, Box<dyn std::error::Error>> {
     // Use the program ID instead of event pubkey
-    let program = client.program(Pubkey::from_str("9B1F56Dx649qbEDRbQAXZtmPXTFrLaYjTXBuCeZWMJ1x")?)?;
+    let program_id = Pubkey::from_str("9B1F56Dx649qbEDRbQAXZtmPXTFrLaYjTXBuCeZWMJ1x")?;
+    let program = client.program(program_id)?;
     
     let (url, tag) = image_params.unwrap_or_else(|| (
         "https://example.com/image.jpg".to_string(),
         "default".to_string(),
     ));
+    let content_hash = hash(url.as_bytes()).to_bytes();
+
+    upload_image_with_storage(
+        join_accounts,
+        authority,
+        eventsnap::StorageRef::Http(url),
+        content_hash,
+        tag,
+        client,
+    )
+}
+
+pub fn upload_image_with_storage(
+    join_accounts: &JoinEventAccounts,
+    authority: &Keypair,
+    storage: eventsnap::StorageRef,
+    content_hash: [u8; 32],
+    tag: String,
+    client: &Client<Arc<Keypair>>,
+) -> Result<ImageUploadAccounts, Box<dyn std::error::Error>> {
+    let program_id = Pubkey::from_str("9B1F56Dx649qbEDRbQAXZtmPXTFrLaYjTXBuCeZWMJ1x")?;
+    let program = client.program(program_id)?;
 
     let tx = program
         .request()
         .accounts(eventsnap::accounts::UploadImageWithTag {
+            program_data: program_data_pda(&program_id),
             event: join_accounts.event,
-            user_data: join_accounts.user_data.pubkey(),
+            user_data: join_accounts.user_data,
+            member_role: member_role_account(&program, &join_accounts.event, &authority.pubkey()),
             authority: authority.pubkey(),
+            system_program: anchor_lang::solana_program::system_program::ID,
         })
         .args(eventsnap::instruction::UploadImageWithTag {
-            url: url.clone(),
+            storage: storage.clone(),
+            content_hash,
             tag: tag.clone(),
         })
         .signer(authority)
@@ -283,10 +707,11 @@ pub fn upload_image(
     println!("Upload image transaction signature: {}", tx);
 
     Ok(ImageUploadAccounts {
-        url,
+        url: storage.uri(),
+        content_hash,
         tag,
         event: join_accounts.event,
-        user_data: join_accounts.user_data.pubkey(),
+        user_data: join_accounts.user_data,
         last_signature: tx.to_string(),
     })
 }
@@ -299,3 +724,447 @@ pub fn upload_default_image(
 ) -> Result<ImageUploadAccounts, Box<dyn std::error::Error>> {
     upload_image(join_accounts, authority, client, None)
 }
+
+pub struct ImageApprovalAccounts {
+    pub highlight_page: Pubkey,
+    pub last_signature: String,
+}
+
+pub fn approve_image(
+    program_accounts: &ProgramAccounts,
+    image_upload: &ImageUploadAccounts,
+    image_index: u32,
+    client: &Client<Arc<Keypair>>,
+) -> Result<ImageApprovalAccounts, Box<dyn std::error::Error>> {
+    let program = client.program(program_accounts.program_id)?;
+    let oracle = oracle_keypair();
+
+    let event: eventsnap::Event = program.account(image_upload.event)?;
+    let highlight_page = highlight_page_pda(
+        &program_accounts.program_id,
+        &image_upload.event,
+        event.next_highlight_page(),
+    );
+
+    let tx = program
+        .request()
+        .accounts(eventsnap::accounts::ApproveImage {
+            program_data: program_accounts.program_data,
+            event: image_upload.event,
+            highlight_page,
+            user_data: image_upload.user_data,
+            oracle: oracle.pubkey(),
+            system_program: anchor_lang::solana_program::system_program::ID,
+        })
+        .args(eventsnap::instruction::ApproveImage { image_index })
+        .signer(&oracle)
+        .send()?;
+
+    println!("Approve image transaction signature: {}", tx);
+
+    Ok(ImageApprovalAccounts {
+        highlight_page,
+        last_signature: tx.to_string(),
+    })
+}
+
+pub fn set_paused(
+    program_accounts: &ProgramAccounts,
+    paused: u8,
+    client: &Client<Arc<Keypair>>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let program = client.program(program_accounts.program_id)?;
+    let admin = admin_keypair();
+
+    let tx = program
+        .request()
+        .accounts(eventsnap::accounts::UpdateProgramData {
+            program_data: program_accounts.program_data,
+            owner: admin.pubkey(),
+        })
+        .args(eventsnap::instruction::SetPaused { paused })
+        .signer(&admin)
+        .send()?;
+
+    println!("Set paused transaction signature: {}", tx);
+
+    Ok(tx.to_string())
+}
+
+// Collects every event of type `T` emitted by the given transaction by
+// decoding its "Program data:" log lines
+pub fn parse_events<T: anchor_lang::Event>(
+    client: &Client<Arc<Keypair>>,
+    program_id: Pubkey,
+    signature: &str,
+) -> Result<Vec<T>, Box<dyn std::error::Error>> {
+    let program = client.program(program_id)?;
+    let tx = program.rpc().get_transaction_with_config(
+        &Signature::from_str(signature)?,
+        RpcTransactionConfig {
+            encoding: None,
+            commitment: Some(CommitmentConfig::confirmed()),
+            max_supported_transaction_version: Some(0),
+        },
+    )?;
+
+    let logs = tx.transaction.meta
+        .and_then(|meta| Option::<Vec<String>>::from(meta.log_messages))
+        .unwrap_or_default();
+
+    let mut events = vec![];
+    for log in logs {
+        let Some(encoded) = log.strip_prefix("Program data: ") else {
+            continue;
+        };
+        let data = base64::engine::general_purpose::STANDARD.decode(encoded)?;
+        if data.len() >= 8 && data[..8] == T::DISCRIMINATOR {
+            events.push(T::try_from_slice(&data[8..])?);
+        }
+    }
+
+    Ok(events)
+}
+
+pub fn grant_role(
+    event_accounts: &EventAccounts,
+    owner: &Keypair,
+    member: &Pubkey,
+    role: eventsnap::Role,
+    client: &Client<Arc<Keypair>>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let program_id = Pubkey::from_str("9B1F56Dx649qbEDRbQAXZtmPXTFrLaYjTXBuCeZWMJ1x")?;
+    let program = client.program(program_id)?;
+
+    let tx = program
+        .request()
+        .accounts(eventsnap::accounts::GrantRole {
+            event: event_accounts.event,
+            member_role: member_role_pda(&program_id, &event_accounts.event, member),
+            authority: owner.pubkey(),
+            system_program: anchor_lang::solana_program::system_program::ID,
+        })
+        .args(eventsnap::instruction::GrantRole {
+            member: *member,
+            role,
+        })
+        .signer(owner)
+        .send()?;
+
+    println!("Grant role transaction signature: {}", tx);
+
+    Ok(tx.to_string())
+}
+
+// Attendee page holding `member`, plus the tail page if that is a different one
+pub fn find_attendee_page(
+    program: &Program<Arc<Keypair>>,
+    event: &Pubkey,
+    member: &Pubkey,
+) -> Result<(Pubkey, Option<Pubkey>), Box<dyn std::error::Error>> {
+    let event_account: eventsnap::Event = program.account(*event)?;
+    let tail_page_no = event_account.attendee_tail_page();
+    for page_no in 0..event_account.attendee_page_count() {
+        let page = attendee_page_pda(&program.id(), event, page_no);
+        let attendees: eventsnap::AttendeePage = program.account(page)?;
+        if attendees.attendees.contains(member) {
+            let tail = (page_no != tail_page_no)
+                .then(|| attendee_page_pda(&program.id(), event, tail_page_no));
+            return Ok((page, tail));
+        }
+    }
+    Err("Member is not an attendee".into())
+}
+
+// Leaves the event, passing `highlight_pages` as the pages to purge
+pub fn leave_event(
+    join_accounts: &JoinEventAccounts,
+    authority: &Keypair,
+    client: &Client<Arc<Keypair>>,
+    keep_highlights: bool,
+    highlight_pages: &[Pubkey],
+) -> Result<String, Box<dyn std::error::Error>> {
+    let program_id = Pubkey::from_str("9B1F56Dx649qbEDRbQAXZtmPXTFrLaYjTXBuCeZWMJ1x")?;
+    let program = client.program(program_id)?;
+
+    let (attendee_page, tail_attendee_page) = find_attendee_page(&program, &join_accounts.event, &authority.pubkey())?;
+
+    let tx = program
+        .request()
+        .accounts(eventsnap::accounts::LeaveEvent {
+            event: join_accounts.event,
+            attendee_page,
+            tail_attendee_page,
+            user_data: join_accounts.user_data,
+            authority: authority.pubkey(),
+        })
+        .accounts(
+            highlight_pages
+                .iter()
+                .map(|page| AccountMeta::new(*page, false))
+                .collect::<Vec<_>>(),
+        )
+        .args(eventsnap::instruction::LeaveEvent { keep_highlights })
+        .signer(authority)
+        .send()?;
+
+    println!("Leave event transaction signature: {}", tx);
+
+    Ok(tx.to_string())
+}
+
+pub fn set_event_status(
+    program_accounts: &ProgramAccounts,
+    event_accounts: &EventAccounts,
+    authority: &Keypair,
+    status: eventsnap::EventStatus,
+    client: &Client<Arc<Keypair>>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let program = client.program(program_accounts.program_id)?;
+
+    let tx = program
+        .request()
+        .accounts(eventsnap::accounts::SetEventStatus {
+            event: event_accounts.event,
+            vault: vault_pda(&program_accounts.program_id, &event_accounts.event),
+            program_data: program_accounts.program_data,
+            authority: authority.pubkey(),
+        })
+        .args(eventsnap::instruction::SetEventStatus { status })
+        .signer(authority)
+        .send()?;
+
+    println!("Set event status transaction signature: {}", tx);
+
+    Ok(tx.to_string())
+}
+
+pub fn cancel_event(
+    program_accounts: &ProgramAccounts,
+    event_accounts: &EventAccounts,
+    authority: &Keypair,
+    client: &Client<Arc<Keypair>>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let program = client.program(program_accounts.program_id)?;
+
+    let tx = program
+        .request()
+        .accounts(eventsnap::accounts::CancelEvent {
+            event: event_accounts.event,
+            vault: vault_pda(&program_accounts.program_id, &event_accounts.event),
+            program_data: program_accounts.program_data,
+            authority: authority.pubkey(),
+        })
+        .args(eventsnap::instruction::CancelEvent {})
+        .signer(authority)
+        .send()?;
+
+    println!("Cancel event transaction signature: {}", tx);
+
+    Ok(tx.to_string())
+}
+
+// Claims a refund from a cancelled event, passing the token accounts when a
+// token fee was paid so it comes back to the attendee's associated token account
+pub fn claim_refund(
+    join_accounts: &JoinEventAccounts,
+    authority: &Keypair,
+    client: &Client<Arc<Keypair>>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let program_id = Pubkey::from_str("9B1F56Dx649qbEDRbQAXZtmPXTFrLaYjTXBuCeZWMJ1x")?;
+    let program = client.program(program_id)?;
+
+    let (attendee_page, tail_attendee_page) = find_attendee_page(&program, &join_accounts.event, &authority.pubkey())?;
+    let event: eventsnap::Event = program.account(join_accounts.event)?;
+    let user_data: eventsnap::UserData = program.account(join_accounts.user_data)?;
+    let token_accounts = match event.fee_mint {
+        Some(mint) if user_data.token_fee_paid > 0 => {
+            let token_program = program.rpc().get_account(&mint)?.owner;
+            Some((
+                mint,
+                token_account(&join_accounts.event, &mint, &token_program),
+                token_account(&authority.pubkey(), &mint, &token_program),
+                token_program,
+            ))
+        }
+        _ => None,
+    };
+
+    let tx = program
+        .request()
+        .accounts(eventsnap::accounts::ClaimRefund {
+            event: join_accounts.event,
+            attendee_page,
+            tail_attendee_page,
+            user_data: join_accounts.user_data,
+            vault: vault_pda(&program_id, &join_accounts.event),
+            fee_mint: token_accounts.map(|accounts| accounts.0),
+            event_token_account: token_accounts.map(|accounts| accounts.1),
+            destination: token_accounts.map(|accounts| accounts.2),
+            token_program: token_accounts.map(|accounts| accounts.3),
+            authority: authority.pubkey(),
+        })
+        .args(eventsnap::instruction::ClaimRefund {})
+        .signer(authority)
+        .send()?;
+
+    println!("Claim refund transaction signature: {}", tx);
+
+    Ok(tx.to_string())
+}
+
+// Mints the attendee's badge, returning its mint
+pub fn claim_badge(
+    join_accounts: &JoinEventAccounts,
+    authority: &Keypair,
+    client: &Client<Arc<Keypair>>,
+) -> Result<Pubkey, Box<dyn std::error::Error>> {
+    let program_id = Pubkey::from_str("9B1F56Dx649qbEDRbQAXZtmPXTFrLaYjTXBuCeZWMJ1x")?;
+    let program = client.program(program_id)?;
+    let badge_mint = badge_mint_pda(&program_id, &join_accounts.user_data);
+
+    let tx = program
+        .request()
+        .accounts(eventsnap::accounts::ClaimBadge {
+            event: join_accounts.event,
+            user_data: join_accounts.user_data,
+            badge_mint,
+            badge_account: token_account(&authority.pubkey(), &badge_mint, &spl_token_2022::ID),
+            authority: authority.pubkey(),
+            token_program: spl_token_2022::ID,
+            associated_token_program: spl_associated_token_account::ID,
+            system_program: anchor_lang::solana_program::system_program::ID,
+        })
+        .args(eventsnap::instruction::ClaimBadge {})
+        .signer(authority)
+        .send()?;
+
+    println!("Claim badge transaction signature: {}", tx);
+
+    Ok(badge_mint)
+}
+
+pub fn create_photo_collection(
+    event_accounts: &EventAccounts,
+    authority: &Keypair,
+    royalty_bps: u16,
+    uploader_share: u8,
+    client: &Client<Arc<Keypair>>,
+) -> Result<Pubkey, Box<dyn std::error::Error>> {
+    let program_id = Pubkey::from_str("9B1F56Dx649qbEDRbQAXZtmPXTFrLaYjTXBuCeZWMJ1x")?;
+    let program = client.program(program_id)?;
+    let collection_mint = collection_mint_pda(&program_id, &event_accounts.event);
+
+    let tx = program
+        .request()
+        .accounts(eventsnap::accounts::CreatePhotoCollection {
+            event: event_accounts.event,
+            collection_mint,
+            authority: authority.pubkey(),
+            token_program: spl_token_2022::ID,
+            system_program: anchor_lang::solana_program::system_program::ID,
+        })
+        .args(eventsnap::instruction::CreatePhotoCollection { royalty_bps, uploader_share })
+        .signer(authority)
+        .send()?;
+
+    println!("Create photo collection transaction signature: {}", tx);
+
+    Ok(collection_mint)
+}
+
+// Mints one of the uploader's images as an NFT, returning its mint
+pub fn mint_image(
+    join_accounts: &JoinEventAccounts,
+    authority: &Keypair,
+    image_index: u32,
+    client: &Client<Arc<Keypair>>,
+) -> Result<Pubkey, Box<dyn std::error::Error>> {
+    let program_id = Pubkey::from_str("9B1F56Dx649qbEDRbQAXZtmPXTFrLaYjTXBuCeZWMJ1x")?;
+    let program = client.program(program_id)?;
+    let image_mint = Keypair::new();
+
+    let tx = program
+        .request()
+        .accounts(eventsnap::accounts::MintImage {
+            event: join_accounts.event,
+            user_data: join_accounts.user_data,
+            collection_mint: collection_mint_pda(&program_id, &join_accounts.event),
+            image_mint: image_mint.pubkey(),
+            image_account: token_account(&authority.pubkey(), &image_mint.pubkey(), &spl_token_2022::ID),
+            authority: authority.pubkey(),
+            token_program: spl_token_2022::ID,
+            associated_token_program: spl_associated_token_account::ID,
+            system_program: anchor_lang::solana_program::system_program::ID,
+        })
+        .args(eventsnap::instruction::MintImage { image_index })
+        .signer(authority)
+        .signer(&image_mint)
+        .send()?;
+
+    println!("Mint image transaction signature: {}", tx);
+
+    Ok(image_mint.pubkey())
+}
+
+pub fn create_image_tree(
+    event_accounts: &EventAccounts,
+    authority: &Keypair,
+    max_depth: u8,
+    max_buffer_size: u8,
+    client: &Client<Arc<Keypair>>,
+) -> Result<Pubkey, Box<dyn std::error::Error>> {
+    let program_id = Pubkey::from_str("9B1F56Dx649qbEDRbQAXZtmPXTFrLaYjTXBuCeZWMJ1x")?;
+    let program = client.program(program_id)?;
+    let image_tree = image_tree_pda(&program_id, &event_accounts.event);
+
+    let tx = program
+        .request()
+        .accounts(eventsnap::accounts::CreateImageTree {
+            event: event_accounts.event,
+            image_tree,
+            authority: authority.pubkey(),
+            system_program: anchor_lang::solana_program::system_program::ID,
+        })
+        .args(eventsnap::instruction::CreateImageTree { max_depth, max_buffer_size })
+        .signer(authority)
+        .send()?;
+
+    println!("Create image tree transaction signature: {}", tx);
+
+    Ok(image_tree)
+}
+
+pub fn append_image(
+    join_accounts: &JoinEventAccounts,
+    authority: &Keypair,
+    storage: &eventsnap::StorageRef,
+    tag: &str,
+    content_hash: [u8; 32],
+    client: &Client<Arc<Keypair>>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let program_id = Pubkey::from_str("9B1F56Dx649qbEDRbQAXZtmPXTFrLaYjTXBuCeZWMJ1x")?;
+    let program = client.program(program_id)?;
+
+    let tx = program
+        .request()
+        .accounts(eventsnap::accounts::AppendImage {
+            program_data: program_data_pda(&program_id),
+            event: join_accounts.event,
+            image_tree: image_tree_pda(&program_id, &join_accounts.event),
+            user_data: join_accounts.user_data,
+            member_role: member_role_account(&program, &join_accounts.event, &authority.pubkey()),
+            authority: authority.pubkey(),
+        })
+        .args(eventsnap::instruction::AppendImage {
+            storage: storage.clone(),
+            tag: tag.to_string(),
+            content_hash,
+        })
+        .signer(authority)
+        .send()?;
+
+    println!("Append image transaction signature: {}", tx);
+
+    Ok(tx.to_string())
+}